@@ -1,10 +1,29 @@
-pub use self::{bitmap::*, camera::*, collision::*, config::*};
+pub use self::{
+    assets::OverrideAssetPlugin, audio::*, bitmap::*, camera::*, collision::*, config::*,
+    controller::*, cutscene::*, input::*, music::*, output::*, rng::*, sfxr::*, text::*,
+    tracker::*, transition::*, tween::*,
+};
+pub(crate) use self::{assets::*, fps::*};
 use bevy::prelude::*;
 
+mod assets;
+mod audio;
 mod bitmap;
 mod camera;
 mod collision;
 mod config;
+mod controller;
+mod cutscene;
+mod fps;
+mod input;
+mod music;
+mod output;
+mod rng;
+mod sfxr;
+mod text;
+mod tracker;
+mod transition;
+mod tween;
 
 #[derive(Debug)]
 pub struct EnginePlugin;
@@ -13,6 +32,14 @@ impl Plugin for EnginePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(ConfigPlugin)
             .add_plugin(CameraPlugin)
-            .add_plugin(CollisionPlugin);
+            .add_plugin(CameraControllerPlugin)
+            .add_plugin(CollisionPlugin)
+            .add_plugin(RngPlugin)
+            .add_plugin(ActionPlugin)
+            .add_plugin(TweenPlugin)
+            .add_plugin(MixerPlugin)
+            .add_plugin(TrackerPlugin)
+            .add_plugin(SfxrPlugin)
+            .add_plugin(FpsPlugin);
     }
 }
@@ -0,0 +1,146 @@
+use bevy::{
+    asset::{AssetIo, AssetIoError, FileType, Metadata},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use bevy_embedded_assets::EmbeddedAssetIo;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// Adds an [`AssetServer`] that reads embedded assets, unless a file with the same path exists in
+/// one of the override directories.
+///
+/// Must be added before `AssetPlugin`.
+///
+/// Override files are not watched for changes. Assets are read once, when they are first loaded,
+/// so the game must be restarted to pick up an edited file.
+#[derive(Debug, Default)]
+pub struct OverrideAssetPlugin {
+    /// Override directories, searched in order.
+    pub dirs: Vec<PathBuf>,
+}
+
+/// Asset I/O for [`OverrideAssetPlugin`]. Loose files in the override directories replace the
+/// embedded assets with the same path, so art and audio can be changed without a rebuild.
+pub(crate) struct OverrideAssetIo {
+    embedded: EmbeddedAssetIo,
+    dirs: Vec<PathBuf>,
+}
+
+/// Errors from [`read_asset`].
+#[derive(Debug)]
+pub(crate) enum ReadAssetError {
+    /// The asset server does not use [`OverrideAssetIo`].
+    UnsupportedAssetIo,
+    /// The asset could not be read.
+    Io(AssetIoError),
+}
+
+impl Plugin for OverrideAssetPlugin {
+    fn build(&self, app: &mut App) {
+        for dir in &self.dirs {
+            info!("Overriding assets from {}", dir.display());
+        }
+
+        app.insert_resource(AssetServer::new(OverrideAssetIo {
+            embedded: EmbeddedAssetIo::preloaded(),
+            dirs: self.dirs.clone(),
+        }));
+    }
+}
+
+impl OverrideAssetIo {
+    /// Find the first override directory that has the given path.
+    fn find(&self, path: &Path) -> Option<PathBuf> {
+        self.dirs
+            .iter()
+            .map(|dir| dir.join(path))
+            .find(|path| path.exists())
+    }
+
+    fn load_path_sync(&self, path: &Path) -> Result<Vec<u8>, AssetIoError> {
+        match self.find(path) {
+            Some(file) if file.is_file() => Ok(fs::read(file)?),
+            _ => self.embedded.load_path_sync(path),
+        }
+    }
+}
+
+impl AssetIo for OverrideAssetIo {
+    fn load_path<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move { self.load_path_sync(path) })
+    }
+
+    fn read_directory(
+        &self,
+        path: &Path,
+    ) -> Result<Box<dyn Iterator<Item = PathBuf>>, AssetIoError> {
+        let mut entries: Vec<_> = self
+            .embedded
+            .read_directory(path)
+            .map(|entries| entries.collect())
+            .unwrap_or_default();
+
+        // Add the files that only exist in the override directories.
+        for dir in &self.dirs {
+            let Ok(read_dir) = fs::read_dir(dir.join(path)) else {
+                continue;
+            };
+            for entry in read_dir.flatten() {
+                let entry = path.join(entry.file_name());
+                if !entries.contains(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    fn get_metadata(&self, path: &Path) -> Result<Metadata, AssetIoError> {
+        match self.find(path) {
+            Some(file) if file.is_dir() => Ok(Metadata::new(FileType::Directory)),
+            Some(_) => Ok(Metadata::new(FileType::File)),
+            None => self.embedded.get_metadata(path),
+        }
+    }
+
+    // Hot reloading is not supported. See `OverrideAssetPlugin`.
+    fn watch_path_for_changes(&self, _path: &Path) -> Result<(), AssetIoError> {
+        Ok(())
+    }
+
+    fn watch_for_changes(&self) -> Result<(), AssetIoError> {
+        Ok(())
+    }
+}
+
+/// Synchronously read the raw bytes of an asset, preferring the override directories.
+pub(crate) fn read_asset(
+    asset_server: &AssetServer,
+    path: &str,
+) -> Result<Vec<u8>, ReadAssetError> {
+    let io = asset_server
+        .asset_io()
+        .downcast_ref::<OverrideAssetIo>()
+        .ok_or(ReadAssetError::UnsupportedAssetIo)?;
+
+    io.load_path_sync(Path::new(path))
+        .map_err(ReadAssetError::Io)
+}
+
+impl fmt::Display for ReadAssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnsupportedAssetIo => write!(
+                f,
+                "Assets can only be read synchronously with OverrideAssetIo"
+            ),
+            Self::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for ReadAssetError {}
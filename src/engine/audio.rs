@@ -0,0 +1,390 @@
+use crate::engine::{Bitmap, Camera, ConfigState, Parallax, ScreenSpace, SfxrVariants};
+use ahash::HashMap;
+use bevy::prelude::*;
+use bevy_kira_audio::prelude::*;
+use std::{collections::VecDeque, time::Duration};
+
+#[derive(Debug)]
+pub(crate) struct MixerPlugin;
+
+/// Audio channel for background music.
+#[derive(Debug, Resource)]
+pub struct MusicChannel;
+
+/// Audio channel for sound effects. Play them with the [`SfxPlayer`], which sets the volume of
+/// each sound from the config when it starts.
+#[derive(Debug, Resource)]
+pub struct SfxChannel;
+
+/// Audio channel for user interface sounds, like menu blips.
+#[derive(Debug, Resource)]
+pub struct UiChannel;
+
+/// Audio channel for dialogue lines and announcements, like boss warnings. It uses the sound
+/// effect volume. Cutscene `Dialogue` cues play on it.
+#[derive(Debug, Resource)]
+pub struct DialogueChannel;
+
+/// The `Ducking` resource lowers the music volume while other audio is playing, so it can be
+/// heard over the music.
+#[derive(Debug, Resource)]
+pub struct Ducking {
+    /// The music is lowered by the largest amount of all active rules.
+    pub rules: Vec<DuckingRule>,
+    held: bool,
+    level: f32,
+    release_rate: f32,
+}
+
+/// Lowers the music while its trigger is active.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DuckingRule {
+    pub trigger: DuckTrigger,
+    /// How much the music volume is lowered, from 0 (not at all) to 1 (silent).
+    pub amount: f32,
+    /// Time in seconds to lower the music when the trigger becomes active.
+    pub attack: f32,
+    /// Time in seconds to restore the music after the trigger becomes inactive.
+    pub release: f32,
+}
+
+/// Activates a [`DuckingRule`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DuckTrigger {
+    /// While a sound is playing on the [`DialogueChannel`].
+    Dialogue,
+    /// While [`Ducking::hold`] is in effect, e.g. in a pause menu.
+    Held,
+}
+
+/// The `SfxPlayer` resource plays sound effects on the [`SfxChannel`] with voice limiting.
+///
+/// Each sound can only have a few instances playing at once. When a sound reaches its limit, its
+/// oldest instance is stopped to make room. Triggers of a sound that was just played are merged,
+/// so dozens of identical triggers in one frame only play once. Variations from
+/// [`Sfxr::mutated`](crate::engine::Sfxr::mutated) count as the sound they were made from.
+#[derive(Debug, Resource)]
+pub struct SfxPlayer {
+    /// Maximum number of instances of a sound that can play at once, unless the sound has its own
+    /// limit.
+    pub max_voices: usize,
+    /// Triggers of the same sound within this many seconds are merged.
+    pub dedupe_window: f32,
+    limits: HashMap<Handle<AudioSource>, usize>,
+    voices: HashMap<Handle<AudioSource>, Voices>,
+    requests: Vec<SfxRequest>,
+}
+
+/// Playing instances of a single sound.
+#[derive(Debug)]
+struct Voices {
+    instances: VecDeque<Handle<AudioInstance>>,
+    last_played: f64,
+}
+
+#[derive(Debug)]
+struct SfxRequest {
+    sound: Handle<AudioSource>,
+    panning: f32,
+    volume: f32,
+}
+
+/// Adding this component to an entity lets it play positional sound effects with the
+/// [`SfxPlayer`].
+///
+/// Sounds are panned by the entity's horizontal position on screen, and fade out as it moves off
+/// screen. Entities with a [`Bitmap`] are positioned by the center of the bitmap.
+#[derive(Component, Debug, Default)]
+pub struct AudioEmitter {
+    sounds: Vec<Handle<AudioSource>>,
+}
+
+/// Emitters with new sounds to play, and everything needed to position them on screen.
+type EmitterQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut AudioEmitter,
+        &'static Transform,
+        Option<&'static Bitmap>,
+        Option<&'static Parallax>,
+        Option<&'static ScreenSpace>,
+    ),
+    Changed<AudioEmitter>,
+>;
+
+/// How far sounds are panned at the screen edges, from 0 (centered) to 1 (fully left or right).
+const PAN_WIDTH: f32 = 0.8;
+
+/// Distance in pixels outside the screen where positional sounds become inaudible.
+const ATTENUATION_DISTANCE: f32 = 64.0;
+
+/// Fade out duration in seconds for voices that are stolen, to avoid clicks.
+const STEAL_FADE: f32 = 0.02;
+
+impl Plugin for MixerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_channel::<MusicChannel>()
+            .add_audio_channel::<SfxChannel>()
+            .add_audio_channel::<UiChannel>()
+            .add_audio_channel::<DialogueChannel>()
+            .init_resource::<SfxPlayer>()
+            .init_resource::<Ducking>()
+            .add_system(Self::update)
+            .add_system(Self::emit.before(Self::play))
+            .add_system(Self::play);
+    }
+}
+
+impl MixerPlugin {
+    /// Keep the channel volumes in sync with the config and ducking. Every channel is scaled by
+    /// the master volume.
+    fn update(
+        mut ducking: ResMut<Ducking>,
+        config: Res<ConfigState>,
+        music: Res<AudioChannel<MusicChannel>>,
+        ui: Res<AudioChannel<UiChannel>>,
+        dialogue: Res<AudioChannel<DialogueChannel>>,
+        time: Res<Time>,
+    ) {
+        let held = ducking.held;
+        let ducked = ducking.update(time.delta_seconds(), |trigger| match trigger {
+            DuckTrigger::Dialogue => dialogue.is_playing_sound(),
+            DuckTrigger::Held => held,
+        });
+
+        let master = config.master_volume();
+        if config.is_changed() || ducked {
+            let duck = 1.0 - ducking.level;
+            music.set_volume((master * config.music_volume() * duck) as f64);
+        }
+        // Sound effects carry their own volume, which replaces the channel volume.
+        if config.is_changed() {
+            ui.set_volume((master * config.ui_volume()) as f64);
+            dialogue.set_volume((master * config.sfx_volume()) as f64);
+        }
+    }
+
+    /// Play the sounds triggered by each [`AudioEmitter`] from its position on screen.
+    fn emit(mut emitters: EmitterQuery, mut player: ResMut<SfxPlayer>, camera: Res<Camera>) {
+        let size = *camera.size();
+
+        for (mut emitter, transform, bitmap, parallax, screen_space) in &mut emitters {
+            if emitter.sounds.is_empty() {
+                continue;
+            }
+
+            let pos = match (screen_space, parallax) {
+                (Some(_), _) => transform.translation.truncate(),
+                (None, Some(parallax)) => {
+                    camera.parallax_to_screen(transform.translation, parallax)
+                }
+                (None, None) => camera.world_to_screen(transform.translation),
+            };
+            let center = pos
+                + bitmap.map_or(Vec2::ZERO, |bitmap| {
+                    Vec2::new(bitmap.width() as f32, bitmap.height() as f32) / 2.0
+                });
+
+            let panning = 0.5 + (center.x / size.x - 0.5).clamp(-0.5, 0.5) * PAN_WIDTH;
+            let outside = (-center).max(center - size).max(Vec2::ZERO).length();
+            let volume = 1.0 - (outside / ATTENUATION_DISTANCE).min(1.0);
+
+            // Draining must not mark the emitter as changed, or it would be visited again.
+            for sound in emitter.bypass_change_detection().sounds.drain(..) {
+                if volume > 0.0 {
+                    player.push(sound, panning, volume);
+                }
+            }
+        }
+    }
+
+    /// Play the sounds requested from the [`SfxPlayer`], stealing voices when needed.
+    fn play(
+        mut player: ResMut<SfxPlayer>,
+        mut instances: ResMut<Assets<AudioInstance>>,
+        config: Res<ConfigState>,
+        variants: Res<SfxrVariants>,
+        sfx: Res<AudioChannel<SfxChannel>>,
+        time: Res<Time>,
+    ) {
+        let player = &mut *player;
+        let now = time.elapsed_seconds_f64();
+        let mixer_volume = config.master_volume() * config.sfx_volume();
+        let window = player.dedupe_window as f64;
+
+        // Forget voices that finished playing.
+        player.voices.retain(|_, voices| {
+            voices
+                .instances
+                .retain(|instance| sfx.state(instance) != PlaybackState::Stopped);
+
+            !voices.instances.is_empty() || now - voices.last_played < window
+        });
+
+        for request in std::mem::take(&mut player.requests) {
+            let sound = variants.sound_of(&request.sound).unwrap_or(&request.sound);
+            let limit = player
+                .limits
+                .get(sound)
+                .copied()
+                .unwrap_or(player.max_voices);
+            let voices = player
+                .voices
+                .entry(sound.clone())
+                .or_insert_with(|| Voices {
+                    instances: VecDeque::new(),
+                    last_played: f64::NEG_INFINITY,
+                });
+            if limit == 0 || now - voices.last_played < window {
+                continue;
+            }
+
+            while voices.instances.len() >= limit {
+                let Some(oldest) = voices.instances.pop_front() else {
+                    break;
+                };
+                if let Some(instance) = instances.get_mut(&oldest) {
+                    instance.stop(AudioTween::linear(Duration::from_secs_f32(STEAL_FADE)));
+                }
+            }
+
+            let instance = sfx
+                .play(request.sound)
+                .with_panning(request.panning as f64)
+                .with_volume((request.volume * mixer_volume) as f64)
+                .handle();
+            voices.instances.push_back(instance);
+            voices.last_played = now;
+        }
+    }
+}
+
+impl Default for SfxPlayer {
+    fn default() -> Self {
+        Self {
+            max_voices: 4,
+            dedupe_window: 0.05,
+            limits: HashMap::default(),
+            voices: HashMap::default(),
+            requests: Vec::new(),
+        }
+    }
+}
+
+impl Default for Ducking {
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                DuckingRule {
+                    trigger: DuckTrigger::Dialogue,
+                    amount: 0.6,
+                    attack: 0.1,
+                    release: 0.5,
+                },
+                DuckingRule {
+                    trigger: DuckTrigger::Held,
+                    amount: 0.5,
+                    attack: 0.2,
+                    release: 0.5,
+                },
+            ],
+            held: false,
+            level: 0.0,
+            release_rate: f32::INFINITY,
+        }
+    }
+}
+
+impl Ducking {
+    /// Activate the [`DuckTrigger::Held`] rules until [`Ducking::release`] is called.
+    pub fn hold(&mut self) {
+        self.held = true;
+    }
+
+    /// Deactivate the [`DuckTrigger::Held`] rules.
+    pub fn release(&mut self) {
+        self.held = false;
+    }
+
+    /// How much the music is currently lowered, from 0 to 1.
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    /// Move the level towards the largest amount of all active rules. Returns `true` if the level
+    /// changed.
+    fn update(&mut self, dt: f32, is_active: impl Fn(DuckTrigger) -> bool) -> bool {
+        let strongest = self
+            .rules
+            .iter()
+            .filter(|rule| is_active(rule.trigger))
+            .max_by(|a, b| a.amount.total_cmp(&b.amount))
+            .copied();
+
+        let previous = self.level;
+        let target = match strongest {
+            Some(rule) => {
+                let amount = rule.amount.clamp(0.0, 1.0);
+                if amount > self.level {
+                    self.level = approach(self.level, amount, rate(amount, rule.attack) * dt);
+                }
+                self.release_rate = rate(amount, rule.release);
+
+                amount
+            }
+            None => 0.0,
+        };
+        if target < self.level {
+            self.level = approach(self.level, target, self.release_rate * dt);
+        }
+
+        self.level != previous
+    }
+}
+
+/// Rate of change per second to move by `amount` in `time` seconds.
+fn rate(amount: f32, time: f32) -> f32 {
+    if time > 0.0 {
+        amount / time
+    } else {
+        f32::INFINITY
+    }
+}
+
+/// Move `value` towards `target` by at most `step`.
+fn approach(value: f32, target: f32, step: f32) -> f32 {
+    if value < target {
+        (value + step).min(target)
+    } else {
+        (value - step).max(target)
+    }
+}
+
+impl SfxPlayer {
+    /// Play a sound effect, centered at full volume.
+    pub fn play(&mut self, sound: Handle<AudioSource>) {
+        self.push(sound, 0.5, 1.0);
+    }
+
+    /// Set the maximum number of instances of a sound that can play at once, overriding
+    /// [`SfxPlayer::max_voices`].
+    pub fn set_max_voices(&mut self, sound: &Handle<AudioSource>, max_voices: usize) {
+        self.limits.insert(sound.clone(), max_voices);
+    }
+
+    fn push(&mut self, sound: Handle<AudioSource>, panning: f32, volume: f32) {
+        self.requests.push(SfxRequest {
+            sound,
+            panning,
+            volume,
+        });
+    }
+}
+
+impl AudioEmitter {
+    /// Play a sound from this entity's position.
+    pub fn play(&mut self, sound: Handle<AudioSource>) {
+        self.sounds.push(sound);
+    }
+}
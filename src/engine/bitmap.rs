@@ -1,11 +1,13 @@
-use crate::engine::{BvhResource, Camera, ScreenSpace};
+use crate::engine::{
+    project, read_asset, rotate, BvhResource, Camera, CameraView, LayerMask, Output, Parallax,
+    ReadAssetError, ScreenSpace,
+};
 use ahash::{HashSet, RandomState};
-use bevy::prelude::*;
-use bevy_embedded_assets::EmbeddedAssetIo;
+use bevy::{asset::AssetIoError, ecs::system::SystemParam, prelude::*};
 use bevy_pixels::prelude::*;
 use bvh_arena::volumes::Aabb;
 use pix::{ops::SrcOver, rgb::Rgba8p, Raster};
-use std::{collections::HashMap, io::Cursor, path::Path, sync::Arc};
+use std::{borrow::Cow, collections::HashMap, fmt, io::Cursor, path::PathBuf, sync::Arc};
 
 #[derive(Debug)]
 pub(crate) struct BitmapPlugin;
@@ -20,6 +22,36 @@ pub struct Bitmap {
 #[derive(Component, Debug)]
 pub struct Tiled;
 
+/// Multiplies the color of a `Bitmap` when it is drawn. The factors are red, green, blue, and
+/// alpha, in the range `[0, 1]`.
+#[derive(Copy, Clone, Component, Debug, PartialEq)]
+pub struct Tint(pub Vec4);
+
+/// Coarse draw order for a `Bitmap`, independent of its position. Layers are drawn from back to
+/// front in declaration order.
+///
+/// Bitmaps without a [`LayerMask`] are also only drawn by cameras that include their render
+/// layer.
+#[derive(Copy, Clone, Component, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum RenderLayer {
+    Background,
+    #[default]
+    Gameplay,
+    Foreground,
+    Hud,
+}
+
+/// Fine draw order for a `Bitmap` within its [`RenderLayer`]. Higher values are drawn on top.
+///
+/// Bitmaps without a `SortOrder` are ordered by their Z coordinate.
+#[derive(Copy, Clone, Component, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct SortOrder(pub i32);
+
+/// Controls the transparency of a `Bitmap` when it is drawn. `0.0` is invisible and `1.0` is
+/// opaque.
+#[derive(Component, Debug)]
+pub struct Opacity(pub f32);
+
 #[derive(Debug)]
 struct TileIter {
     current: i32,
@@ -32,6 +64,22 @@ pub struct BitmapCache {
     map: HashMap<String, Bitmap, RandomState>,
 }
 
+/// Errors from loading a `Bitmap`.
+#[derive(Debug)]
+pub enum BitmapError {
+    /// The file does not exist.
+    NotFound(PathBuf),
+    /// The file could not be read.
+    Io(AssetIoError),
+    /// Bitmaps can only be loaded through
+    /// [`OverrideAssetPlugin`](crate::engine::OverrideAssetPlugin).
+    UnsupportedAssetIo,
+    /// The file is not a valid PNG.
+    Decode(png::DecodingError),
+    /// The PNG uses a color type that is not supported.
+    UnsupportedColorType(png::ColorType),
+}
+
 impl Plugin for BitmapPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<BitmapCache>()
@@ -39,109 +87,303 @@ impl Plugin for BitmapPlugin {
     }
 }
 
-impl Camera {
-    fn to_aabb(&self) -> Aabb<2> {
-        let pos = self.transform().translation.truncate();
-        let size = *self.size();
-
-        Aabb::from_min_max(pos, pos + size)
-    }
-}
-
-/// `ScreenEntities` are always drawn because they are either tiled or drawn in screen space.
-type ScreenEntities = (
+/// Everything needed to draw a `Bitmap`.
+type BitmapQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Bitmap,
+        &'static Transform,
+        Option<&'static Tiled>,
+        Option<&'static ScreenSpace>,
+        Option<&'static Tint>,
+        Option<&'static Opacity>,
+        Option<&'static LayerMask>,
+        Option<&'static RenderLayer>,
+        Option<&'static SortOrder>,
+        Option<&'static Parallax>,
+    ),
+>;
+
+/// `TiledEntities` are always drawn by every camera because they fill the viewport.
+type TiledEntities = (
     With<Bitmap>,
     With<Transform>,
-    Or<(With<Tiled>, With<ScreenSpace>)>,
+    With<Tiled>,
+    Without<ScreenSpace>,
 );
 
+/// `ParallaxEntities` are culled per camera because their position depends on the camera.
+type ParallaxEntities = (Without<Tiled>, Without<ScreenSpace>);
+
+/// `ScreenEntities` are drawn once, on top of every camera.
+type ScreenEntities = (With<Bitmap>, With<Transform>, With<ScreenSpace>);
+
+/// The world, as seen by the cameras.
+#[derive(SystemParam)]
+struct Drawables<'w, 's> {
+    bvh: Res<'w, BvhResource>,
+    query: BitmapQuery<'w, 's>,
+    tiled_entities: Query<'w, 's, Entity, TiledEntities>,
+    parallax_entities: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Bitmap,
+            &'static Transform,
+            &'static Parallax,
+        ),
+        ParallaxEntities,
+    >,
+}
+
+/// What a call to `draw` composites.
+#[derive(Copy, Clone, Debug)]
+enum Pass {
+    /// World space bitmaps on the given layers, as seen from a camera translation.
+    World(Vec3, LayerMask),
+    /// Screen space bitmaps.
+    Screen,
+}
+
 impl BitmapPlugin {
     /// Rasterizes all [`Bitmap`]s in the world.
     ///
     /// Each [`Bitmap`] requires a [`Transform`] (to position it), and may optionally include a
     /// [`ScreenSpace`] component to control whether the position is affected by the viewport
-    /// position. The [`Camera`] resource provides the viewport.
+    /// position, a [`Parallax`] component to scale how far it scrolls, [`Tint`] or [`Opacity`]
+    /// components to modulate its color, and a [`LayerMask`] to choose the cameras that draw it.
+    ///
+    /// The world is drawn by the [`Camera`] resource and by every [`CameraView`] entity, in
+    /// ascending order. The main camera has order 0. Screen space bitmaps are drawn last, on top
+    /// of every camera.
     fn update(
         mut pixels_res: ResMut<PixelsResource>,
         mut camera: ResMut<Camera>,
-        bvh: Res<BvhResource>,
-        query: Query<(&Bitmap, &Transform, Option<&Tiled>, Option<&ScreenSpace>)>,
+        output: Res<Output>,
+        world: Drawables,
         screen_entities: Query<Entity, ScreenEntities>,
+        views: Query<(&CameraView, &Transform)>,
     ) {
-        let camera_transform = camera.transform();
-        let camera_aabb = camera.to_aabb();
-        let camera_raster = camera.raster_mut();
+        let mut views: Vec<_> = views.iter().collect();
+        views.sort_by_key(|(view, _)| view.order);
+        let (below, above): (Vec<_>, Vec<_>) =
+            views.into_iter().partition(|(view, _)| view.order < 0);
+
+        let camera_translation = camera.render_transform().translation;
+        let camera_angle = camera.shake_angle();
+        let camera_layers = camera.layers();
+        let camera_active = camera.is_active();
+        let frame = camera.raster_mut();
 
         // Clear the camera.
-        camera_raster.clear();
+        frame.clear();
+
+        for (view, transform) in below {
+            world.draw_view(frame, view, transform);
+        }
+
+        if camera_active {
+            let entities = world.visible_entities(frame, camera_translation);
+            let pass = Pass::World(camera_translation, camera_layers);
+            if camera_angle.abs() < f32::EPSILON {
+                draw(frame, pass, entities, &world.query);
+            } else {
+                // Only the world shakes, so it is rotated before compositing. Views and screen
+                // space bitmaps stay steady.
+                let mut raster = Raster::with_clear(frame.width(), frame.height());
+                draw(&mut raster, pass, entities, &world.query);
+                rotate(&mut raster, camera_angle);
+                frame.composite_raster((0, 0), &raster, (), SrcOver);
+            }
+        }
+
+        for (view, transform) in above {
+            world.draw_view(frame, view, transform);
+        }
+
+        draw(frame, Pass::Screen, screen_entities.iter(), &world.query);
+
+        camera.apply_mosaic();
+
+        // Scale the camera into `Pixels`, applying the post-processing filter.
+        output.present(camera.raster(), pixels_res.pixels.get_frame_mut());
+    }
+}
+
+impl Drawables<'_, '_> {
+    /// Find the world space entities that may be visible in a raster, as seen from the camera
+    /// translation.
+    fn visible_entities(&self, raster: &Raster<Rgba8p>, translation: Vec3) -> HashSet<Entity> {
+        let pos = translation.truncate();
+        let size = Vec2::new(raster.width() as f32, raster.height() as f32);
+        let aabb = Aabb::from_min_max(pos, pos + size);
 
-        // Use a HashSet to de-dupe entities. `ScreenEntities` are always drawn.
-        let mut entities = HashSet::from_iter(&screen_entities);
+        // Use a HashSet to de-dupe entities. Tiled entities are always drawn.
+        let mut entities = HashSet::from_iter(&self.tiled_entities);
 
         // Find all `Bitmap` entities that are within the viewport.
-        bvh.for_each_overlaps(&camera_aabb, |&entity| {
+        self.bvh.for_each_overlaps(&aabb, |&entity| {
             entities.insert(entity);
         });
 
-        // Sort by Z coordinate
-        let mut bitmaps: Vec<_> = entities
-            .into_iter()
-            .map(|entity| query.get(entity).unwrap())
-            .collect();
-        bitmaps.sort_unstable_by_key(|query| (query.1.translation.z * 1000.0) as i64);
+        // Parallax entities are not in the BVH. Cull them where they appear on the screen.
+        for (entity, bitmap, transform, parallax) in &self.parallax_entities {
+            let min = project(translation, transform.translation, parallax.factor);
+            let max = min + Vec2::new(bitmap.width() as f32, bitmap.height() as f32);
+            if max.x > 0.0 && max.y > 0.0 && min.x < size.x && min.y < size.y {
+                entities.insert(entity);
+            }
+        }
 
-        // Composite each bitmap to the camera.
-        for (bitmap, transform, tiled, screen_space) in bitmaps {
-            let (x, y) = if screen_space.is_some() {
-                // In screen space, the destination region is relative to the origin.
-                let translation = transform.translation;
+        entities
+    }
 
-                (translation.x as i32, translation.y as i32)
-            } else {
-                // In world space, the destination region is relative to the camera viewport.
-                let z = transform.translation.z;
-                let z = if z.is_finite() { z } else { 1.0 };
-                let camera_translation = transform.translation - camera_transform.translation * z;
+    /// Draw the world into a separate raster for a view, then composite it into the frame.
+    fn draw_view(&self, frame: &mut Raster<Rgba8p>, view: &CameraView, transform: &Transform) {
+        let size = view.rect.size().max(Vec2::ZERO).as_uvec2();
+        if size.x == 0 || size.y == 0 {
+            return;
+        }
 
-                (camera_translation.x as i32, camera_translation.y as i32)
-            };
+        let mut raster = Raster::with_clear(size.x, size.y);
+        let translation = transform.translation;
+        let entities = self.visible_entities(&raster, translation);
+        let pass = Pass::World(translation, view.layers);
+        draw(&mut raster, pass, entities, &self.query);
 
-            if tiled.is_some() {
-                let width = camera_raster.width();
-                let height = camera_raster.height();
+        let pos = view.rect.min;
+        frame.composite_raster((pos.x as i32, pos.y as i32), &raster, (), SrcOver);
+    }
+}
 
-                // Iterate over all ranges required to fill the frame with the bitmap.
-                for x in bitmap.tile_cols(x, width) {
-                    for y in bitmap.tile_rows(y, height) {
-                        camera_raster.composite_raster((x, y), &bitmap.raster, (), SrcOver);
-                    }
+/// Composite bitmap entities into a raster, sorted by [`RenderLayer`] and then by [`SortOrder`].
+/// Bitmaps without a `SortOrder` are sorted by their Z coordinate.
+fn draw(
+    raster: &mut Raster<Rgba8p>,
+    pass: Pass,
+    entities: impl IntoIterator<Item = Entity>,
+    query: &BitmapQuery,
+) {
+    let mut bitmaps: Vec<_> = entities
+        .into_iter()
+        .filter_map(|entity| query.get(entity).ok())
+        .filter(|query| match pass {
+            Pass::World(_, layers) => {
+                let render_layer = query.7.copied().unwrap_or_default();
+                let mask = query.6.copied().unwrap_or_else(|| render_layer.into());
+
+                query.3.is_none() && mask.intersects(layers)
+            }
+            Pass::Screen => query.3.is_some(),
+        })
+        .collect();
+    bitmaps.sort_unstable_by_key(|query| {
+        let render_layer = query.7.copied().unwrap_or_default();
+        let order = query.8.map_or_else(
+            || (query.1.translation.z * 1000.0) as i64,
+            |order| order.0 as i64,
+        );
+
+        (render_layer, order)
+    });
+
+    // Composite each bitmap to the raster.
+    for (bitmap, transform, tiled, _, tint, opacity, .., parallax) in bitmaps {
+        let bitmap_raster = bitmap.modulated(tint, opacity);
+
+        let pos = match pass {
+            // In world space, the destination region is relative to the camera viewport.
+            Pass::World(camera_translation, _) => {
+                let factor = parallax.map_or(Vec2::ONE, |parallax| parallax.factor);
+
+                project(camera_translation, transform.translation, factor)
+            }
+            // In screen space, the destination region is relative to the origin.
+            Pass::Screen => transform.translation.truncate(),
+        };
+        let (x, y) = (pos.x as i32, pos.y as i32);
+
+        if tiled.is_some() {
+            let width = raster.width();
+            let height = raster.height();
+
+            // Iterate over all ranges required to fill the frame with the bitmap.
+            for x in bitmap.tile_cols(x, width) {
+                for y in bitmap.tile_rows(y, height) {
+                    raster.composite_raster((x, y), &bitmap_raster, (), SrcOver);
                 }
-            } else {
-                camera_raster.composite_raster((x, y), &bitmap.raster, (), SrcOver);
             }
+        } else {
+            raster.composite_raster((x, y), &bitmap_raster, (), SrcOver);
         }
-
-        // Copy the camera to `Pixels`.
-        pixels_res
-            .pixels
-            .get_frame_mut()
-            .copy_from_slice(camera_raster.as_u8_slice());
     }
 }
 
+impl Tint {
+    pub const WHITE: Self = Self(Vec4::ONE);
+    pub const YELLOW: Self = Self(Vec4::new(1.0, 1.0, 0.0, 1.0));
+}
+
 impl Bitmap {
-    fn new(bytes: &[u8]) -> Self {
-        let decoder = png::Decoder::new(Cursor::new(bytes));
-        let mut reader = decoder.read_info().unwrap();
+    fn new(bytes: &[u8]) -> Result<Self, BitmapError> {
+        let mut decoder = png::Decoder::new(Cursor::new(bytes));
+        // Expand palettes and low bit depths to 8 bits, turn `tRNS` chunks into an alpha channel,
+        // and strip 16-bit samples down to 8 bits. Every image is 8-bit RGB, RGBA, grayscale, or
+        // grayscale with alpha after this.
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
         let mut buf = vec![0; reader.output_buffer_size()];
-        let info = reader.next_frame(&mut buf).unwrap();
-        let raster = Arc::new(Raster::with_u8_buffer(
-            info.width,
-            info.height,
-            &buf[..info.buffer_size()],
-        ));
+        let info = reader.next_frame(&mut buf)?;
+        let buf = &buf[..info.buffer_size()];
+
+        // Convert to RGBA.
+        let mut pixels: Vec<u8> = match info.color_type {
+            png::ColorType::Rgba => buf.to_vec(),
+            png::ColorType::Rgb => buf
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buf
+                .chunks_exact(2)
+                .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+                .collect(),
+            png::ColorType::Grayscale => buf.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+            // Palettes were expanded to RGB or RGBA by the transformations above, so this only
+            // happens if the png crate stops doing that.
+            png::ColorType::Indexed => {
+                return Err(BitmapError::UnsupportedColorType(info.color_type))
+            }
+        };
 
-        Self { raster }
+        // PNG alpha is straight, but rasters are premultiplied.
+        for pixel in pixels.chunks_exact_mut(4) {
+            let alpha = pixel[3] as u16;
+            for channel in &mut pixel[..3] {
+                *channel = ((*channel as u16 * alpha + 127) / 255) as u8;
+            }
+        }
+
+        let raster = Arc::new(Raster::with_u8_buffer(info.width, info.height, pixels));
+
+        Ok(Self { raster })
+    }
+
+    /// A magenta and black checkerboard that stands in for bitmaps that fail to load.
+    fn placeholder() -> Self {
+        let mut bitmap = Self::with_clear(16, 16);
+        let magenta = Rgba8p::new(1.0, 0.0, 1.0, 1.0);
+        let black = Rgba8p::new(0.0, 0.0, 0.0, 1.0);
+        bitmap.fill_with(|x, y| {
+            if (x / 4 + y / 4) % 2 == 0 {
+                magenta
+            } else {
+                black
+            }
+        });
+
+        bitmap
     }
 
     pub fn with_clear(width: u32, height: u32) -> Self {
@@ -160,6 +402,17 @@ impl Bitmap {
         self.raster = Arc::new(Raster::with_color(self.width(), self.height(), color));
     }
 
+    /// Replace every pixel with the color returned by `f(x, y)`.
+    pub fn fill_with<F: FnMut(u32, u32) -> Rgba8p>(&mut self, mut f: F) {
+        let width = self.width();
+        let raster = Arc::make_mut(&mut self.raster);
+
+        for (i, pixel) in raster.pixels_mut().iter_mut().enumerate() {
+            let i = i as u32;
+            *pixel = f(i % width, i / width);
+        }
+    }
+
     pub fn width(&self) -> u32 {
         self.raster.width()
     }
@@ -168,6 +421,28 @@ impl Bitmap {
         self.raster.height()
     }
 
+    /// Get the raster with the tint and opacity applied.
+    fn modulated(&self, tint: Option<&Tint>, opacity: Option<&Opacity>) -> Cow<'_, Raster<Rgba8p>> {
+        let [r, g, b, a] = tint.map_or([1.0; 4], |tint| tint.0.to_array());
+        let a = a * opacity.map_or(1.0, |opacity| opacity.0);
+
+        // Colors are pre-multiplied, so the alpha scales every channel.
+        let factors = [r * a, g * a, b * a, a].map(|factor| factor.clamp(0.0, 1.0));
+        if factors == [1.0; 4] {
+            return Cow::Borrowed(&self.raster);
+        }
+
+        let buffer: Vec<_> = self
+            .raster
+            .as_u8_slice()
+            .iter()
+            .zip(factors.iter().cycle())
+            .map(|(&chan, factor)| (chan as f32 * factor) as u8)
+            .collect();
+
+        Cow::Owned(Raster::with_u8_buffer(self.width(), self.height(), buffer))
+    }
+
     fn tile_rows(&self, start: i32, height: u32) -> impl Iterator<Item = i32> {
         let step = self.height().try_into().unwrap();
         let current = start % step;
@@ -203,20 +478,183 @@ impl Iterator for TileIter {
 }
 
 impl BitmapCache {
+    /// Get a bitmap by asset path, loading it the first time it is requested.
+    ///
+    /// Errors are logged. Bitmaps that fail to load are replaced with a placeholder in debug
+    /// builds, and an empty bitmap in release builds.
     pub fn get_or_create(&mut self, key: &str, asset_server: &Res<AssetServer>) -> Bitmap {
-        self.map
-            .entry(key.to_string())
-            .or_insert_with(|| {
-                let io = asset_server
-                    .asset_io()
-                    .downcast_ref::<EmbeddedAssetIo>()
-                    .unwrap();
-
-                // TODO: This should probably return the Result.
-                let image = io.load_path_sync(Path::new(key)).unwrap();
-
-                Bitmap::new(&image)
-            })
-            .clone()
+        self.load(key, asset_server).unwrap_or_else(|err| {
+            error!("Unable to load bitmap {key}: {err}");
+
+            let bitmap = if cfg!(debug_assertions) {
+                Bitmap::placeholder()
+            } else {
+                Bitmap::with_clear(1, 1)
+            };
+            self.map.insert(key.to_string(), bitmap.clone());
+
+            bitmap
+        })
+    }
+
+    /// Get a bitmap by asset path, loading it the first time it is requested.
+    pub fn load(
+        &mut self,
+        key: &str,
+        asset_server: &Res<AssetServer>,
+    ) -> Result<Bitmap, BitmapError> {
+        if let Some(bitmap) = self.map.get(key) {
+            return Ok(bitmap.clone());
+        }
+
+        let image = read_asset(asset_server, key).map_err(|err| match err {
+            ReadAssetError::UnsupportedAssetIo => BitmapError::UnsupportedAssetIo,
+            ReadAssetError::Io(AssetIoError::NotFound(path)) => BitmapError::NotFound(path),
+            ReadAssetError::Io(err) => BitmapError::Io(err),
+        })?;
+        let bitmap = Bitmap::new(&image)?;
+        self.map.insert(key.to_string(), bitmap.clone());
+
+        Ok(bitmap)
+    }
+}
+
+impl fmt::Display for BitmapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotFound(path) => write!(f, "File not found: {}", path.display()),
+            Self::Io(err) => write!(f, "{err}"),
+            Self::UnsupportedAssetIo => {
+                write!(f, "Bitmaps can only be loaded through OverrideAssetPlugin")
+            }
+            Self::Decode(err) => write!(f, "PNG decoding error: {err}"),
+            Self::UnsupportedColorType(color_type) => {
+                write!(f, "Unsupported PNG color type: {color_type:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BitmapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<png::DecodingError> for BitmapError {
+    fn from(err: png::DecodingError) -> Self {
+        Self::Decode(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode a 2x1 PNG.
+    fn encode(
+        color_type: png::ColorType,
+        bit_depth: png::BitDepth,
+        palette: Option<(&[u8], &[u8])>,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+        encoder.set_color(color_type);
+        encoder.set_depth(bit_depth);
+        if let Some((palette, trns)) = palette {
+            encoder.set_palette(palette);
+            encoder.set_trns(trns);
+        }
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(data).unwrap();
+        writer.finish().unwrap();
+
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Vec<u8> {
+        let bitmap = Bitmap::new(bytes).unwrap();
+        assert_eq!((bitmap.width(), bitmap.height()), (2, 1));
+
+        bitmap.raster.as_u8_slice().to_vec()
+    }
+
+    #[test]
+    fn rgb_is_opaque() {
+        let png = encode(
+            png::ColorType::Rgb,
+            png::BitDepth::Eight,
+            None,
+            &[255, 0, 0, 10, 20, 30],
+        );
+
+        assert_eq!(decode(&png), [255, 0, 0, 255, 10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn rgba_is_premultiplied() {
+        let png = encode(
+            png::ColorType::Rgba,
+            png::BitDepth::Eight,
+            None,
+            &[255, 255, 255, 128, 200, 100, 50, 0],
+        );
+
+        assert_eq!(decode(&png), [128, 128, 128, 128, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn grayscale_is_expanded() {
+        let png = encode(
+            png::ColorType::Grayscale,
+            png::BitDepth::Eight,
+            None,
+            &[0, 200],
+        );
+
+        assert_eq!(decode(&png), [0, 0, 0, 255, 200, 200, 200, 255]);
+    }
+
+    #[test]
+    fn grayscale_alpha_is_premultiplied() {
+        let png = encode(
+            png::ColorType::GrayscaleAlpha,
+            png::BitDepth::Eight,
+            None,
+            &[255, 64, 100, 255],
+        );
+
+        assert_eq!(decode(&png), [64, 64, 64, 64, 100, 100, 100, 255]);
+    }
+
+    #[test]
+    fn indexed_uses_palette_transparency() {
+        let palette = [255, 0, 0, 0, 0, 255];
+        let trns = [128, 255];
+        let png = encode(
+            png::ColorType::Indexed,
+            png::BitDepth::Eight,
+            Some((&palette, &trns)),
+            &[0, 1],
+        );
+
+        assert_eq!(decode(&png), [128, 0, 0, 128, 0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn sixteen_bit_is_stripped() {
+        // Samples are multiples of 257, so stripping and scaling to 8 bits agree.
+        let mut data = Vec::new();
+        for sample in [0xffff_u16, 0, 0, 0x8080, 0, 0x0101, 0xffff, 0xffff] {
+            data.extend_from_slice(&sample.to_be_bytes());
+        }
+        let png = encode(png::ColorType::Rgba, png::BitDepth::Sixteen, None, &data);
+
+        assert_eq!(decode(&png), [128, 0, 0, 128, 0, 1, 255, 255]);
     }
 }
@@ -1,4 +1,4 @@
-use crate::engine::{Bitmap, BitmapPlugin, ConfigState};
+use crate::engine::{Bitmap, BitmapPlugin, ConfigState, OutputPlugin, RenderLayer, RngSource};
 use bevy::prelude::*;
 use bevy_pixels::*;
 use pix::{
@@ -20,19 +20,81 @@ pub(crate) struct FadePlugin;
 pub struct Camera {
     viewport: Viewport,
     raster: Raster<Rgba8p>,
+    mosaic: u32,
+    shake: Shake,
+    trauma: Trauma,
+    layers: LayerMask,
+    active: bool,
 }
 
+/// A `CameraView` draws the world into a rectangle of the screen, in addition to the main
+/// [`Camera`]. Use several for split screen, or a small one for a minimap or a close-up inset.
+///
+/// The entity's [`Transform`] translation is the world space position of the view's top-left
+/// corner.
+#[derive(Component, Debug)]
+pub struct CameraView {
+    /// The screen space rectangle that the view is drawn into.
+    pub rect: Rect,
+    /// Views are composited in ascending order. The main camera has order 0.
+    pub order: i32,
+    /// Only bitmaps on these layers are drawn.
+    pub layers: LayerMask,
+}
+
+/// A set of up to 32 render layers.
+///
+/// On a `Bitmap`, it selects the layers the bitmap is on; bitmaps without a `LayerMask` are on the
+/// layer of their [`RenderLayer`]. On a camera, it selects the layers that the camera draws.
+#[derive(Copy, Clone, Component, Debug, Eq, PartialEq)]
+pub struct LayerMask(pub u32);
+
 #[derive(Debug)]
 struct Viewport {
     transform: Transform,
     size: Vec2,
 }
 
+/// Trauma-based screen shake settings.
+///
+/// Trauma is added with [`Camera::add_trauma`] and decays over time. The shake is proportional to
+/// the square of the trauma, so small amounts are subtle and large amounts are violent.
+#[derive(Clone, Debug)]
+pub struct Shake {
+    /// Maximum translation offset in pixels, at full trauma.
+    pub max_offset: Vec2,
+    /// Maximum rotation in radians, at full trauma. Zero disables rotation.
+    pub max_angle: f32,
+    /// Trauma removed per second.
+    pub decay: f32,
+    /// How quickly the shake changes direction, in Hz.
+    pub frequency: f32,
+}
+
+/// The current screen shake.
+#[derive(Debug, Default)]
+struct Trauma {
+    amount: f32,
+    time: f32,
+    offset: Vec2,
+    angle: f32,
+}
+
 /// Adding this component to a `Bitmap` will cause the entity's [`Transform`] to be interpreted in
 /// screen space.
 #[derive(Component, Debug)]
 pub struct ScreenSpace;
 
+/// Adding this component to a `Bitmap` scales how far it scrolls with the camera, for parallax
+/// backgrounds and foregrounds.
+///
+/// A factor of `1.0` moves with the world, `0.0` stays fixed on the screen, and values greater
+/// than `1.0` scroll faster than the world. Bitmaps without a `Parallax` use a factor of `1.0`.
+#[derive(Clone, Component, Debug)]
+pub struct Parallax {
+    pub factor: Vec2,
+}
+
 #[derive(Component, Debug)]
 pub struct Fade {
     timer: Timer,
@@ -47,6 +109,7 @@ pub struct FadeBundle {
     bitmap: Bitmap,
     transform: Transform,
     screen_space: ScreenSpace,
+    render_layer: RenderLayer,
 }
 
 impl Plugin for CameraPlugin {
@@ -60,13 +123,170 @@ impl Plugin for CameraPlugin {
         };
         let raster = Raster::<Rgba8p>::with_clear(width, height);
 
-        app.insert_resource(Camera { viewport, raster })
-            .add_plugin(PixelsPlugin { width, height })
-            .add_plugin(BitmapPlugin)
-            .add_plugin(FadePlugin);
+        app.insert_resource(Camera {
+            viewport,
+            raster,
+            mosaic: 1,
+            shake: Shake::default(),
+            trauma: Trauma::default(),
+            layers: LayerMask::ALL,
+            active: true,
+        })
+        .add_plugin(PixelsPlugin { width, height })
+        .add_plugin(BitmapPlugin)
+        .add_plugin(FadePlugin)
+        .add_plugin(OutputPlugin)
+        .add_system(Self::resize)
+        .add_system_to_stage(CoreStage::PostUpdate, Self::shake);
+    }
+}
+
+impl CameraPlugin {
+    /// Resize the viewport when the screen resolution changes.
+    fn resize(mut camera: ResMut<Camera>, config: Res<ConfigState>) {
+        if !config.is_changed() {
+            return;
+        }
+
+        let (width, height) = config.screen_resolution();
+        if camera.raster.width() == width && camera.raster.height() == height {
+            return;
+        }
+
+        camera.viewport.size = Vec2::new(width as f32, height as f32);
+        camera.raster = Raster::with_clear(width, height);
+    }
+
+    /// Decay trauma and update the screen shake offsets.
+    fn shake(mut camera: ResMut<Camera>, time: Res<Time>) {
+        let Camera { shake, trauma, .. } = &mut *camera;
+        if trauma.amount <= 0.0 {
+            trauma.offset = Vec2::ZERO;
+            trauma.angle = 0.0;
+            return;
+        }
+
+        trauma.time += time.delta_seconds();
+        trauma.amount = (trauma.amount - shake.decay * time.delta_seconds()).max(0.0);
+
+        let power = trauma.amount * trauma.amount;
+        let t = trauma.time * shake.frequency;
+        trauma.offset = Vec2::new(noise(0, t), noise(1, t)) * shake.max_offset * power;
+        trauma.angle = noise(2, t) * shake.max_angle * power;
+    }
+}
+
+impl Parallax {
+    /// Create a parallax factor that is the same on both axes.
+    pub fn splat(factor: f32) -> Self {
+        Self {
+            factor: Vec2::splat(factor),
+        }
+    }
+}
+
+impl CameraView {
+    /// Create a view that draws every layer into a rectangle of the screen.
+    pub fn new(rect: Rect, order: i32) -> Self {
+        Self {
+            rect,
+            order,
+            layers: LayerMask::ALL,
+        }
     }
 }
 
+impl LayerMask {
+    /// Every layer.
+    pub const ALL: Self = Self(u32::MAX);
+
+    /// A mask with a single layer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `layer` is 32 or more.
+    pub const fn layer(layer: u32) -> Self {
+        assert!(layer < u32::BITS, "Layer out of range");
+
+        Self(1 << layer)
+    }
+
+    /// Add a layer to the mask.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `layer` is 32 or more.
+    pub const fn with(self, layer: u32) -> Self {
+        Self(self.0 | Self::layer(layer).0)
+    }
+
+    /// Returns `true` if the masks share any layers.
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl From<RenderLayer> for LayerMask {
+    fn from(layer: RenderLayer) -> Self {
+        Self::layer(layer as u32)
+    }
+}
+
+impl Default for Shake {
+    fn default() -> Self {
+        Self {
+            max_offset: Vec2::splat(8.0),
+            max_angle: 0.0,
+            decay: 1.0,
+            frequency: 20.0,
+        }
+    }
+}
+
+/// Project a world space position to screen space relative to the camera translation.
+pub(crate) fn project(camera: Vec3, translation: Vec3, factor: Vec2) -> Vec2 {
+    translation.truncate() - camera.truncate() * factor
+}
+
+/// Rotate a raster around its center by an angle in radians. Uncovered corners are cleared.
+pub(crate) fn rotate(raster: &mut Raster<Rgba8p>, angle: f32) {
+    if angle.abs() < f32::EPSILON {
+        return;
+    }
+
+    let source = raster.clone();
+    let width = source.width() as i32;
+    let height = source.height() as i32;
+    let center = Vec2::new(width as f32, height as f32) / 2.0;
+    let (sin, cos) = (-angle).sin_cos();
+    let pixels = source.pixels();
+
+    for (i, pixel) in raster.pixels_mut().iter_mut().enumerate() {
+        let pos = Vec2::new((i as i32 % width) as f32, (i as i32 / width) as f32) + 0.5;
+        let rel = pos - center;
+        let src = Vec2::new(rel.x * cos - rel.y * sin, rel.x * sin + rel.y * cos) + center;
+        let (x, y) = (src.x.floor() as i32, src.y.floor() as i32);
+
+        *pixel = if (0..width).contains(&x) && (0..height).contains(&y) {
+            pixels[(y * width + x) as usize]
+        } else {
+            Rgba8p::default()
+        };
+    }
+}
+
+/// Smooth 1D value noise in `[-1, 1]`. Each channel is an independent noise signal.
+fn noise(channel: u64, t: f32) -> f32 {
+    let i = t.floor();
+    let f = t - i;
+    let sample = |i: i64| RngSource::new((channel << 32) ^ i as u64).f32() * 2.0 - 1.0;
+
+    let a = sample(i as i64);
+    let b = sample(i as i64 + 1);
+
+    a + (b - a) * f * f * (3.0 - 2.0 * f)
+}
+
 impl Camera {
     /// Get the viewport transformation matrix.
     pub fn transform(&self) -> Transform {
@@ -78,16 +298,132 @@ impl Camera {
         &mut self.viewport.transform
     }
 
+    /// Get the viewport transformation matrix with the screen shake applied.
+    ///
+    /// This is only used for drawing. Culling and gameplay use the logical [`Camera::transform`].
+    pub fn render_transform(&self) -> Transform {
+        let mut transform = self.viewport.transform;
+        transform.translation += self.trauma.offset.extend(0.0);
+
+        transform
+    }
+
+    /// Convert a world space position to screen space.
+    ///
+    /// The Z coordinate only affects draw order, so it is ignored.
+    pub fn world_to_screen(&self, translation: Vec3) -> Vec2 {
+        project(self.viewport.transform.translation, translation, Vec2::ONE)
+    }
+
+    /// Convert a world space position with a [`Parallax`] factor to screen space.
+    pub fn parallax_to_screen(&self, translation: Vec3, parallax: &Parallax) -> Vec2 {
+        project(
+            self.viewport.transform.translation,
+            translation,
+            parallax.factor,
+        )
+    }
+
+    /// Convert a screen space position to world space, at the given Z coordinate.
+    pub fn screen_to_world(&self, position: Vec2, z: f32) -> Vec3 {
+        (position + self.viewport.transform.translation.truncate()).extend(z)
+    }
+
+    /// Returns `true` if a rectangle of the given size at a world space position overlaps the
+    /// screen.
+    pub fn is_on_screen(&self, translation: Vec3, size: Vec2) -> bool {
+        let min = self.world_to_screen(translation);
+        let max = min + size;
+
+        max.x > 0.0 && max.y > 0.0 && min.x < self.viewport.size.x && min.y < self.viewport.size.y
+    }
+
+    /// Add trauma to shake the screen. Trauma is clamped to `[0, 1]`.
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma.amount = (self.trauma.amount + amount).clamp(0.0, 1.0);
+    }
+
+    /// Get the current trauma.
+    pub fn trauma(&self) -> f32 {
+        self.trauma.amount
+    }
+
+    /// Get a mutable reference to the screen shake settings.
+    pub fn shake_mut(&mut self) -> &mut Shake {
+        &mut self.shake
+    }
+
+    /// Get the layers drawn by the main camera.
+    pub fn layers(&self) -> LayerMask {
+        self.layers
+    }
+
+    /// Set the layers drawn by the main camera.
+    pub fn set_layers(&mut self, layers: LayerMask) {
+        self.layers = layers;
+    }
+
+    /// Returns `true` if the main camera draws the world.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Enable or disable drawing the world with the main camera, e.g. when [`CameraView`]s cover
+    /// the whole screen. Screen space bitmaps are always drawn.
+    pub fn set_active(&mut self, active: bool) {
+        self.active = active;
+    }
+
     /// Get the viewport size.
     pub fn size(&self) -> &Vec2 {
         &self.viewport.size
     }
 
+    /// Get a reference to the camera's internal rasterizer.
+    pub fn raster(&self) -> &Raster<Rgba8p> {
+        &self.raster
+    }
+
     /// Get a mutable reference to the camera's internal rasterizer.
     pub fn raster_mut(&mut self) -> &mut Raster<Rgba8p> {
         &mut self.raster
     }
 
+    /// Set the block size for the mosaic (pixelation) effect. A size of 1 disables the effect.
+    pub fn set_mosaic(&mut self, size: u32) {
+        self.mosaic = size.max(1);
+    }
+
+    /// Pixelate the rasterized frame into square blocks of the current mosaic size.
+    pub(crate) fn apply_mosaic(&mut self) {
+        let size = self.mosaic;
+        if size <= 1 {
+            return;
+        }
+
+        let width = self.raster.width();
+        let height = self.raster.height();
+        let pixels = self.raster.pixels_mut();
+
+        for block_y in (0..height).step_by(size as usize) {
+            for block_x in (0..width).step_by(size as usize) {
+                let color = pixels[(block_y * width + block_x) as usize];
+
+                for y in block_y..(block_y + size).min(height) {
+                    let start = (y * width + block_x) as usize;
+                    let end = (y * width + (block_x + size).min(width)) as usize;
+                    pixels[start..end].fill(color);
+                }
+            }
+        }
+    }
+
+    /// Get the screen shake rotation in radians. Like [`Camera::render_transform`], it is only
+    /// applied to the world as seen by the camera.
+    pub(crate) fn shake_angle(&self) -> f32 {
+        self.trauma.angle
+    }
+
     /// Create a component bundle that fades the entire viewport in.
     ///
     /// I.e. the entire viewport is cleared to the given base color which fades to transparent over
@@ -102,12 +438,14 @@ impl Camera {
         };
         let transform = Transform::from_xyz(0.0, 0.0, f32::INFINITY);
         let screen_space = ScreenSpace;
+        let render_layer = RenderLayer::Hud;
 
         FadeBundle {
             bitmap,
             fade,
             transform,
             screen_space,
+            render_layer,
         }
     }
 
@@ -124,12 +462,14 @@ impl Camera {
         };
         let transform = Transform::from_xyz(0.0, 0.0, f32::INFINITY);
         let screen_space = ScreenSpace;
+        let render_layer = RenderLayer::Hud;
 
         FadeBundle {
             bitmap,
             fade,
             transform,
             screen_space,
+            render_layer,
         }
     }
 }
@@ -1,4 +1,4 @@
-use crate::engine::Bitmap;
+use crate::engine::{Bitmap, Parallax};
 use bevy::prelude::*;
 use bvh_arena::{volumes::Aabb, Bvh};
 
@@ -18,7 +18,14 @@ impl Plugin for CollisionPlugin {
 }
 
 impl CollisionPlugin {
-    fn update(mut bvh: ResMut<BvhResource>, query: Query<(Entity, &Bitmap, &Transform)>) {
+    /// Rebuild the BVH from every `Bitmap`.
+    ///
+    /// Bitmaps with a [`Parallax`] component are left out, because where they appear depends on
+    /// the camera that draws them. They are culled per camera instead.
+    fn update(
+        mut bvh: ResMut<BvhResource>,
+        query: Query<(Entity, &Bitmap, &Transform), Without<Parallax>>,
+    ) {
         bvh.clear();
 
         for (entity, bitmap, &transform) in &query {
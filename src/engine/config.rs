@@ -1,11 +1,17 @@
-use crate::consts::*;
+use crate::{
+    consts::*,
+    engine::{Action, KeyBindings, OverrideAssetPlugin},
+};
 use bevy::{
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
     log::LogPlugin,
     prelude::*,
     utils::tracing::Level,
+    window::WindowResizeConstraints,
 };
 use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
 
 #[derive(Debug)]
 pub(crate) struct ConfigPlugin;
@@ -13,24 +19,117 @@ pub(crate) struct ConfigPlugin;
 #[derive(Debug, Resource)]
 pub struct ConfigState {
     dirs: ProjectDirs,
-    ar: AspectRatio,
+    settings: Settings,
     log_config: LogConfig,
     fps: bool,
+    seed: Option<u64>,
 }
 
 #[derive(Debug)]
 pub enum SaveEvent {
     /// Screen aspect ratio.
     AspectRatio(AspectRatio),
+    /// Skip the intro automatically once it has been seen.
+    SkipIntro(bool),
+    /// The intro has been played to the end (or skipped) at least once.
+    IntroSeen,
+    /// Integer window scale.
+    WindowScale(u32),
+    /// Windowed, borderless fullscreen, or exclusive fullscreen.
+    DisplayMode(DisplayMode),
+    /// How the screen is scaled to fit the window.
+    Scaling(Scaling),
+    /// Post-processing filter for a retro look.
+    Filter(Filter),
+    /// Master volume in the range `[0, 1]`. Scales every channel.
+    MasterVolume(f32),
+    /// Music volume in the range `[0, 1]`.
+    MusicVolume(f32),
+    /// Sound effect volume in the range `[0, 1]`.
+    SfxVolume(f32),
+    /// User interface sound volume in the range `[0, 1]`.
+    UiVolume(f32),
+    /// Replace the primary key bound to an action.
+    KeyBinding(Action, KeyCode),
+    /// Show the frame rate on screen.
+    ShowFps(bool),
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
 pub enum AspectRatio {
+    #[default]
     Standard,
     Wide,
     Ultrawide,
 }
 
+/// How the game window is presented.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum DisplayMode {
+    /// A window sized to an integer multiple of the screen resolution.
+    #[default]
+    Windowed,
+    /// A borderless window covering the whole monitor.
+    Borderless,
+    /// Exclusive fullscreen at the monitor's video mode.
+    Fullscreen,
+}
+
+/// How the screen is scaled to fill the window.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Scaling {
+    /// Scale by the largest whole number that fits, with black bars around the screen.
+    #[default]
+    Integer,
+    /// Scale as large as possible while keeping the aspect ratio.
+    Fit,
+    /// Scale to fill the window, ignoring the aspect ratio.
+    Stretch,
+}
+
+/// Post-processing filters, applied at the output scale.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Filter {
+    #[default]
+    None,
+    /// Dark gaps between rows of pixels.
+    Scanlines,
+    /// Scanlines with curved glass and horizontal color bleed.
+    Crt,
+    /// Dark gaps between rows and columns of pixels.
+    Lcd,
+}
+
+/// Settings that are persisted to the config file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+struct Settings {
+    aspect_ratio: AspectRatio,
+    window_scale: u32,
+    display_mode: DisplayMode,
+    scaling: Scaling,
+    filter: Filter,
+    master_volume: f32,
+    music_volume: f32,
+    sfx_volume: f32,
+    ui_volume: f32,
+    show_fps: bool,
+    skip_intro: bool,
+    intro_seen: bool,
+    key_bindings: KeyBindings,
+    /// Fixed RNG seed, e.g. for reproducing a bug.
+    seed: Option<u64>,
+}
+
+/// The beginning of a replay file. Replays are played back from the seed they were recorded with.
+#[derive(Debug, Deserialize)]
+struct ReplayHeader {
+    seed: u64,
+}
+
+/// The largest supported integer window scale.
+pub const MAX_WINDOW_SCALE: u32 = 6;
+
 #[derive(Clone, Debug)]
 struct LogConfig {
     level: Level,
@@ -44,23 +143,111 @@ impl Plugin for ConfigPlugin {
 
         app.insert_resource(config)
             .add_event::<SaveEvent>()
-            .add_system(save_config);
+            .add_system(save_config)
+            .add_system(apply_window.after(save_config))
+            .add_plugin(FrameTimeDiagnosticsPlugin);
 
         if fps {
-            app.add_plugin(FrameTimeDiagnosticsPlugin::default())
-                .add_plugin(LogDiagnosticsPlugin::default());
+            app.add_plugin(LogDiagnosticsPlugin::default());
         }
     }
 }
 
 fn save_config(mut state: ResMut<ConfigState>, mut events: EventReader<SaveEvent>) {
+    if events.is_empty() {
+        return;
+    }
+
     for event in events.iter() {
         match event {
             SaveEvent::AspectRatio(ar) => {
-                state.ar = *ar;
+                state.settings.aspect_ratio = *ar;
+            }
+            SaveEvent::SkipIntro(skip) => {
+                state.settings.skip_intro = *skip;
+            }
+            SaveEvent::IntroSeen => {
+                state.settings.intro_seen = true;
+            }
+            SaveEvent::WindowScale(scale) => {
+                state.settings.window_scale = (*scale).clamp(1, MAX_WINDOW_SCALE);
+            }
+            SaveEvent::DisplayMode(mode) => {
+                state.settings.display_mode = *mode;
+            }
+            SaveEvent::Scaling(scaling) => {
+                state.settings.scaling = *scaling;
+            }
+            SaveEvent::Filter(filter) => {
+                state.settings.filter = *filter;
+            }
+            SaveEvent::MasterVolume(volume) => {
+                state.settings.master_volume = volume.clamp(0.0, 1.0);
+            }
+            SaveEvent::MusicVolume(volume) => {
+                state.settings.music_volume = volume.clamp(0.0, 1.0);
+            }
+            SaveEvent::SfxVolume(volume) => {
+                state.settings.sfx_volume = volume.clamp(0.0, 1.0);
+            }
+            SaveEvent::UiVolume(volume) => {
+                state.settings.ui_volume = volume.clamp(0.0, 1.0);
+            }
+            SaveEvent::KeyBinding(action, key) => {
+                state.settings.key_bindings.bind(*action, *key);
+            }
+            SaveEvent::ShowFps(show) => {
+                state.settings.show_fps = *show;
+                state.fps = false;
             }
         }
     }
+
+    if let Err(err) = state.save() {
+        error!("Unable to save config file: {err}");
+    }
+}
+
+/// Apply the window scale, display mode, and aspect ratio settings whenever they change. Other
+/// settings leave the window alone, so a resized window keeps its size.
+fn apply_window(
+    config: Res<ConfigState>,
+    mut windows: ResMut<Windows>,
+    mut applied: Local<Option<(u32, DisplayMode, AspectRatio)>>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+
+    let next = (
+        config.window_scale(),
+        config.display_mode(),
+        config.aspect_ratio(),
+    );
+    if *applied == Some(next) {
+        return;
+    }
+
+    let Some(window) = windows.get_primary_mut() else {
+        return;
+    };
+
+    *applied = Some(next);
+
+    let mode = config.display_mode().window_mode();
+    if window.mode() != mode {
+        window.set_mode(mode);
+    }
+
+    if mode == WindowMode::Windowed {
+        let (width, height) = config.window_size();
+        window.set_resize_constraints(WindowResizeConstraints {
+            min_width: width,
+            min_height: height,
+            ..default()
+        });
+        window.set_resolution(width, height);
+    }
 }
 
 impl Default for ConfigState {
@@ -68,8 +255,7 @@ impl Default for ConfigState {
         let dirs =
             ProjectDirs::from("com", "BlipJoy", APP_NAME).expect("Could not find home directory");
 
-        // TODO: Load state from the config file
-        let ar = AspectRatio::Standard;
+        let settings = Settings::load(&dirs);
 
         let fps = std::env::var("FPS")
             .ok()
@@ -100,18 +286,83 @@ impl Default for ConfigState {
 
         let log_config = LogConfig { level, filter };
 
+        // The RNG seed is taken from a replay file given with `--replay <PATH>`, the command line
+        // with `--seed <N>`, an env var, or the config file, in that order.
+        let seed = arg_value("--replay")
+            .and_then(|path| ReplayHeader::load(&path))
+            .map(|replay| replay.seed)
+            .or_else(|| {
+                arg_value("--seed")
+                    .or_else(|| std::env::var("SEED").ok())
+                    .and_then(|seed| match seed.parse() {
+                        Ok(seed) => Some(seed),
+                        Err(_) => {
+                            eprintln!("Invalid RNG seed: {seed}");
+                            None
+                        }
+                    })
+            })
+            .or(settings.seed);
+
         Self {
             dirs,
-            ar,
+            settings,
             log_config,
             fps,
+            seed,
         }
     }
 }
 
 impl ConfigState {
     pub fn aspect_ratio(&self) -> AspectRatio {
-        self.ar
+        self.settings.aspect_ratio
+    }
+
+    /// Get the logical window size for the current screen resolution and window scale.
+    pub fn window_size(&self) -> (f32, f32) {
+        let (width, height) = self.screen_resolution();
+        let scale = self.settings.window_scale as f32;
+
+        (width as f32 * scale, height as f32 * scale)
+    }
+
+    pub fn window_scale(&self) -> u32 {
+        self.settings.window_scale
+    }
+
+    pub fn display_mode(&self) -> DisplayMode {
+        self.settings.display_mode
+    }
+
+    pub fn scaling(&self) -> Scaling {
+        self.settings.scaling
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.settings.filter
+    }
+
+    pub fn master_volume(&self) -> f32 {
+        self.settings.master_volume
+    }
+
+    pub fn music_volume(&self) -> f32 {
+        self.settings.music_volume
+    }
+
+    pub fn sfx_volume(&self) -> f32 {
+        self.settings.sfx_volume
+    }
+
+    pub fn ui_volume(&self) -> f32 {
+        self.settings.ui_volume
+    }
+
+    /// Returns `true` if the frame rate should be shown on screen. The `FPS` env var turns it on
+    /// until it is toggled in the options menu.
+    pub fn show_fps(&self) -> bool {
+        self.settings.show_fps || self.fps
     }
 
     pub fn screen_resolution(&self) -> (u32, u32) {
@@ -124,10 +375,179 @@ impl ConfigState {
         (width, HEIGHT)
     }
 
+    /// Returns `true` if the player chose to skip the intro once it has been seen.
+    pub fn skip_intro_enabled(&self) -> bool {
+        self.settings.skip_intro
+    }
+
+    /// Returns `true` if the intro should be skipped without waiting for input.
+    pub fn skip_intro(&self) -> bool {
+        self.settings.skip_intro && self.settings.intro_seen
+    }
+
+    /// Returns `true` once the intro has been seen at least once.
+    pub fn intro_seen(&self) -> bool {
+        self.settings.intro_seen
+    }
+
+    pub fn key_bindings(&self) -> &KeyBindings {
+        &self.settings.key_bindings
+    }
+
+    /// The RNG seed, if one was given in a replay file, on the command line, or in the config file.
+    /// Otherwise the seed is chosen at random.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
     pub fn log_plugin(&self) -> LogPlugin {
         LogPlugin {
             level: self.log_config.level,
             filter: self.log_config.filter.clone(),
         }
     }
+
+    /// Files in the `mods` directory under the data directory replace the embedded assets with the
+    /// same path. Debug builds also read the `assets` directory in the source tree, so art and
+    /// audio changes don't need a rebuild.
+    pub fn asset_plugin(&self) -> OverrideAssetPlugin {
+        let mut dirs = vec![self.dirs.data_dir().join("mods")];
+        if cfg!(debug_assertions) {
+            dirs.push(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets"));
+        }
+        dirs.retain(|dir| dir.is_dir());
+
+        OverrideAssetPlugin { dirs }
+    }
+
+    /// Write the persistent settings to the config file.
+    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Settings::path(&self.dirs);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let config = ron::ser::to_string_pretty(&self.settings, Default::default())?;
+        fs::write(path, config)?;
+
+        Ok(())
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            aspect_ratio: AspectRatio::default(),
+            window_scale: 2,
+            display_mode: DisplayMode::default(),
+            scaling: Scaling::default(),
+            filter: Filter::default(),
+            master_volume: 1.0,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            ui_volume: 1.0,
+            show_fps: false,
+            skip_intro: false,
+            intro_seen: false,
+            key_bindings: KeyBindings::default(),
+            seed: None,
+        }
+    }
+}
+
+impl Settings {
+    fn path(dirs: &ProjectDirs) -> PathBuf {
+        dirs.config_dir().join("config.ron")
+    }
+
+    /// Load settings from the config file, falling back to defaults when it doesn't exist.
+    fn load(dirs: &ProjectDirs) -> Self {
+        let path = Settings::path(dirs);
+
+        match fs::read_to_string(&path) {
+            Ok(config) => ron::from_str(&config).unwrap_or_else(|err| {
+                // Logging is not initialized yet.
+                eprintln!("Unable to parse config file {path:?}: {err}");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+impl ReplayHeader {
+    fn load(path: &str) -> Option<Self> {
+        let replay = fs::read_to_string(path).map_err(|err| err.to_string());
+        match replay.and_then(|replay| ron::from_str(&replay).map_err(|err| err.to_string())) {
+            Ok(replay) => Some(replay),
+            Err(err) => {
+                // Logging is not initialized yet.
+                eprintln!("Unable to read replay file {path:?}: {err}");
+                None
+            }
+        }
+    }
+}
+
+/// Get the value that follows a command line flag, e.g. `--seed <N>`.
+fn arg_value(flag: &str) -> Option<String> {
+    std::env::args().skip_while(|arg| arg != flag).nth(1)
+}
+
+impl AspectRatio {
+    pub const ALL: [Self; 3] = [Self::Standard, Self::Wide, Self::Ultrawide];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Standard => "4:3",
+            Self::Wide => "16:9",
+            Self::Ultrawide => "21:9",
+        }
+    }
+}
+
+impl DisplayMode {
+    pub const ALL: [Self; 3] = [Self::Windowed, Self::Borderless, Self::Fullscreen];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Windowed => "WINDOWED",
+            Self::Borderless => "BORDERLESS",
+            Self::Fullscreen => "FULLSCREEN",
+        }
+    }
+
+    /// The Bevy window mode for this display mode.
+    pub fn window_mode(&self) -> WindowMode {
+        match self {
+            Self::Windowed => WindowMode::Windowed,
+            Self::Borderless => WindowMode::BorderlessFullscreen,
+            Self::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+impl Scaling {
+    pub const ALL: [Self; 3] = [Self::Integer, Self::Fit, Self::Stretch];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Integer => "INTEGER",
+            Self::Fit => "FIT",
+            Self::Stretch => "STRETCH",
+        }
+    }
+}
+
+impl Filter {
+    pub const ALL: [Self; 4] = [Self::None, Self::Scanlines, Self::Crt, Self::Lcd];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::None => "NONE",
+            Self::Scanlines => "SCANLINES",
+            Self::Crt => "CRT",
+            Self::Lcd => "LCD",
+        }
+    }
 }
@@ -0,0 +1,159 @@
+use crate::engine::{Bitmap, Camera};
+use bevy::{math::Rect, prelude::*};
+
+#[derive(Debug)]
+pub(crate) struct CameraControllerPlugin;
+
+/// Adding this component to an entity makes the [`CameraController`] follow it in
+/// [`CameraMode::Follow`]. When there are several targets, the camera follows their midpoint.
+#[derive(Component, Debug)]
+pub struct CameraTarget;
+
+/// The `CameraController` resource moves the [`Camera`] every frame.
+#[derive(Debug, Resource)]
+pub struct CameraController {
+    /// How the camera moves.
+    pub mode: CameraMode,
+    /// Half-size of the rectangle around the viewport center where the target can move freely
+    /// without moving the camera.
+    pub deadzone: Vec2,
+    /// How quickly the camera catches up to the target, per second. Zero snaps to the target.
+    pub smoothing: f32,
+    /// How far ahead of the target to look, in seconds of target velocity.
+    pub look_ahead: Vec2,
+    /// The camera viewport is kept inside this rectangle, e.g. the extents of a tilemap.
+    pub bounds: Option<Rect>,
+    focus: Option<Vec2>,
+    look: Vec2,
+}
+
+/// Camera movement modes for the [`CameraController`].
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum CameraMode {
+    /// The camera is not moved by the controller.
+    #[default]
+    Manual,
+    /// Follow entities with the [`CameraTarget`] component.
+    Follow,
+    /// Scroll at a constant velocity in pixels per second, like a classic shmup stage.
+    AutoScroll(Vec2),
+}
+
+impl Plugin for CameraControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraController>()
+            .add_system_to_stage(CoreStage::PostUpdate, Self::update);
+    }
+}
+
+impl CameraControllerPlugin {
+    fn update(
+        mut controller: ResMut<CameraController>,
+        mut camera: ResMut<Camera>,
+        targets: Query<(&Transform, Option<&Bitmap>), With<CameraTarget>>,
+        time: Res<Time>,
+    ) {
+        let dt = time.delta_seconds();
+        let size = *camera.size();
+        let mut center = camera.transform().translation.truncate() + size / 2.0;
+
+        match controller.mode {
+            CameraMode::Manual => {
+                controller.focus = None;
+                return;
+            }
+            CameraMode::Follow => {
+                // Follow the midpoint of all targets, using the center of their bitmaps.
+                let mut count = 0;
+                let sum = targets
+                    .iter()
+                    .map(|(transform, bitmap)| {
+                        count += 1;
+                        let size = bitmap.map_or(Vec2::ZERO, |bitmap| {
+                            Vec2::new(bitmap.width() as f32, bitmap.height() as f32)
+                        });
+
+                        transform.translation.truncate() + size / 2.0
+                    })
+                    .sum::<Vec2>();
+                if count == 0 {
+                    controller.focus = None;
+                    return;
+                }
+                let focus = sum / count as f32;
+
+                // Look ahead in the direction the target is moving.
+                let velocity = match controller.focus.replace(focus) {
+                    Some(previous) if dt > 0.0 => (focus - previous) / dt,
+                    _ => Vec2::ZERO,
+                };
+                let look = velocity * controller.look_ahead;
+                controller.look = controller.look.lerp(look, controller.blend(dt));
+                let focus = focus + controller.look;
+
+                // Only move when the focus leaves the deadzone.
+                let offset = focus - center;
+                let deadzone = controller.deadzone;
+                let desired = center + offset - offset.clamp(-deadzone, deadzone);
+
+                center = center.lerp(desired, controller.blend(dt));
+            }
+            CameraMode::AutoScroll(velocity) => {
+                center += velocity * dt;
+            }
+        }
+
+        let mut pos = center - size / 2.0;
+        if let Some(bounds) = controller.bounds {
+            pos = clamp_to_bounds(pos, size, bounds);
+        }
+
+        let z = camera.transform().translation.z;
+        camera.transform_mut().translation = pos.extend(z);
+    }
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        Self {
+            mode: CameraMode::default(),
+            deadzone: Vec2::new(16.0, 16.0),
+            smoothing: 8.0,
+            look_ahead: Vec2::ZERO,
+            bounds: None,
+            focus: None,
+            look: Vec2::ZERO,
+        }
+    }
+}
+
+impl CameraController {
+    /// Framerate-independent interpolation factor for the smoothing rate.
+    fn blend(&self, dt: f32) -> f32 {
+        if self.smoothing > 0.0 {
+            1.0 - (-self.smoothing * dt).exp()
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Keep the viewport inside the bounds. If the bounds are smaller than the viewport, the viewport
+/// is centered on them instead.
+fn clamp_to_bounds(pos: Vec2, size: Vec2, bounds: Rect) -> Vec2 {
+    let max = bounds.max - size;
+    let centered = (bounds.min + bounds.max - size) / 2.0;
+
+    Vec2::new(
+        if max.x < bounds.min.x {
+            centered.x
+        } else {
+            pos.x.clamp(bounds.min.x, max.x)
+        },
+        if max.y < bounds.min.y {
+            centered.y
+        } else {
+            pos.y.clamp(bounds.min.y, max.y)
+        },
+    )
+}
@@ -0,0 +1,340 @@
+use crate::engine::{
+    read_asset, BitmapCache, Camera, DialogueChannel, Easing, Effect, ScreenSpace, SfxPlayer,
+    Transition, Tween, TweenTarget,
+};
+use ahash::HashMap;
+use bevy::{ecs::schedule::StateData, prelude::*};
+use bevy_kira_audio::prelude::*;
+use pix::rgb::Rgba8p;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::marker::PhantomData;
+
+/// Plays [`Timeline`] assets for the state type `S`.
+#[derive(Debug)]
+pub(crate) struct CutscenePlugin<S> {
+    marker: PhantomData<S>,
+}
+
+/// The `CutscenePlayer` resource interprets a [`Timeline`] one cue at a time.
+///
+/// Every entity spawned by the timeline is despawned when the player is stopped.
+#[derive(Resource)]
+pub struct CutscenePlayer<S> {
+    request: Option<String>,
+    status: Status,
+    cues: Vec<Cue<S>>,
+    cursor: usize,
+    timer: Timer,
+    /// Spawned entities and their z positions, by id.
+    actors: HashMap<String, (Entity, f32)>,
+    sounds: HashMap<String, Handle<AudioSource>>,
+}
+
+/// A cutscene timeline, loaded from a RON file.
+///
+/// ```ron
+/// (
+///     cues: [
+///         Spawn(id: "logo", image: "images/logo.png", pos: (-40, 50), anchor: Top),
+///         Sfx("sfx/blips.sfxr.json#blip1"),
+///         Wait(0.5),
+///         ChangeState(state: Title, effect: Fade(1.0)),
+///     ],
+/// )
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct Timeline<S> {
+    cues: Vec<Cue<S>>,
+}
+
+/// A single keyed event in a [`Timeline`]. Cues run back-to-back until a `Wait` is reached.
+#[derive(Clone, Debug, Deserialize)]
+pub enum Cue<S> {
+    /// Spawn an image in screen space. The `id` can be used to refer to it later.
+    Spawn {
+        id: String,
+        image: String,
+        pos: (f32, f32),
+        #[serde(default)]
+        anchor: Anchor,
+        #[serde(default = "default_z")]
+        z: f32,
+    },
+    /// Move a spawned image to a new position over time.
+    Move {
+        id: String,
+        to: (f32, f32),
+        #[serde(default)]
+        anchor: Anchor,
+        duration: f32,
+        #[serde(default)]
+        easing: Easing,
+    },
+    /// Despawn a spawned image.
+    Despawn(String),
+    /// Play a sound effect.
+    Sfx(String),
+    /// Play a dialogue line on the [`DialogueChannel`], which lowers the music while it plays.
+    Dialogue(String),
+    /// Fade the screen in from black.
+    FadeIn(f32),
+    /// Fade the screen out to black.
+    FadeOut(f32),
+    /// Wait before running the next cue.
+    Wait(f32),
+    /// Transition to another state.
+    ChangeState { state: S, effect: Effect },
+}
+
+/// The point on the screen that cue positions are relative to.
+#[derive(Copy, Clone, Debug, Default, Deserialize)]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Top,
+    Center,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Status {
+    Idle,
+    Playing,
+    Paused,
+    Finished,
+    Stopping,
+}
+
+/// Marker for entities spawned by a [`CutscenePlayer`].
+#[derive(Component, Debug)]
+struct CutsceneActor;
+
+fn default_z() -> f32 {
+    1.0
+}
+
+impl<S> Default for CutscenePlugin<S> {
+    fn default() -> Self {
+        Self {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<S: StateData + DeserializeOwned> Plugin for CutscenePlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CutscenePlayer::<S>::default())
+            .add_system(Self::update);
+    }
+}
+
+impl<S: StateData + DeserializeOwned> CutscenePlugin<S> {
+    #[allow(clippy::too_many_arguments)]
+    fn update(
+        mut commands: Commands,
+        mut player: ResMut<CutscenePlayer<S>>,
+        mut transition: ResMut<Transition<S>>,
+        mut cache: ResMut<BitmapCache>,
+        asset_server: Res<AssetServer>,
+        mut sfx: ResMut<SfxPlayer>,
+        dialogue: Res<AudioChannel<DialogueChannel>>,
+        camera: Res<Camera>,
+        time: Res<Time>,
+        actors: Query<Entity, With<CutsceneActor>>,
+    ) {
+        // Clean up before loading, so a `stop()` followed by `play()` doesn't leak actors.
+        if player.status == Status::Stopping {
+            for entity in &actors {
+                commands.entity(entity).despawn_recursive();
+            }
+            player.reset();
+        }
+
+        if let Some(path) = player.request.take() {
+            player.load(&path, &asset_server, &mut cache);
+        }
+
+        if player.status != Status::Playing {
+            return;
+        }
+
+        player.timer.tick(time.delta());
+
+        while player.timer.finished() {
+            let Some(cue) = player.cues.get(player.cursor).cloned() else {
+                player.status = Status::Finished;
+                break;
+            };
+            player.cursor += 1;
+
+            let size = *camera.size();
+            match cue {
+                Cue::Spawn {
+                    id,
+                    image,
+                    pos,
+                    anchor,
+                    z,
+                } => {
+                    let bitmap = cache.get_or_create(&image, &asset_server);
+                    let transform = Transform::from_translation(anchor.resolve(pos, size, z));
+                    let entity = commands
+                        .spawn((bitmap, transform, ScreenSpace, CutsceneActor))
+                        .id();
+
+                    player.actors.insert(id, (entity, z));
+                }
+                Cue::Move {
+                    id,
+                    to,
+                    anchor,
+                    duration,
+                    easing,
+                } => {
+                    if let Some(&(entity, z)) = player.actors.get(&id) {
+                        let target = TweenTarget::Translation(anchor.resolve(to, size, z));
+
+                        commands
+                            .entity(entity)
+                            .insert(Tween::new(target, duration, easing));
+                    } else {
+                        warn!("Cutscene cue refers to unknown id: {id}");
+                    }
+                }
+                Cue::Despawn(id) => {
+                    if let Some((entity, _)) = player.actors.remove(&id) {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+                Cue::Sfx(path) => {
+                    if let Some(handle) = player.sounds.get(&path) {
+                        sfx.play(handle.clone());
+                    }
+                }
+                Cue::Dialogue(path) => {
+                    if let Some(handle) = player.sounds.get(&path) {
+                        dialogue.play(handle.clone());
+                    }
+                }
+                Cue::FadeIn(time) | Cue::FadeOut(time) => {
+                    let (width, height) = (size.x as u32, size.y as u32);
+                    let color = Rgba8p::new(0.0, 0.0, 0.0, 1.0);
+                    let fade_bundle = if matches!(cue, Cue::FadeIn(_)) {
+                        Camera::fade_in(time, width, height, color)
+                    } else {
+                        Camera::fade_out(time, width, height, color)
+                    };
+
+                    commands.spawn(fade_bundle).insert(CutsceneActor);
+                }
+                Cue::Wait(time) => {
+                    player.timer = Timer::from_seconds(time, TimerMode::Once);
+                }
+                Cue::ChangeState { state, effect } => {
+                    transition.transition_to(state, effect);
+                }
+            }
+        }
+    }
+}
+
+impl<S> Default for CutscenePlayer<S> {
+    fn default() -> Self {
+        Self {
+            request: None,
+            status: Status::Idle,
+            cues: Vec::new(),
+            cursor: 0,
+            timer: Timer::from_seconds(0.0, TimerMode::Once),
+            actors: HashMap::default(),
+            sounds: HashMap::default(),
+        }
+    }
+}
+
+impl<S: DeserializeOwned> CutscenePlayer<S> {
+    /// Start playing the timeline asset at the given path. Anything spawned by the previous
+    /// timeline is despawned first.
+    pub fn play(&mut self, path: &str) {
+        self.request = Some(path.to_string());
+        if self.status != Status::Idle {
+            self.status = Status::Stopping;
+        }
+    }
+
+    /// Pause the timeline. Spawned entities stay on screen.
+    pub fn pause(&mut self) {
+        if self.status == Status::Playing {
+            self.status = Status::Paused;
+        }
+    }
+
+    /// Resume a paused timeline.
+    pub fn resume(&mut self) {
+        if self.status == Status::Paused {
+            self.status = Status::Playing;
+        }
+    }
+
+    /// Stop the timeline and despawn everything it spawned.
+    pub fn stop(&mut self) {
+        self.request = None;
+        self.status = Status::Stopping;
+    }
+
+    /// Returns `true` after the last cue has run.
+    pub fn is_finished(&self) -> bool {
+        self.status == Status::Finished
+    }
+
+    fn reset(&mut self) {
+        *self = Self {
+            request: self.request.take(),
+            ..default()
+        };
+    }
+
+    /// Load the timeline and preload every image and sound effect it refers to.
+    fn load(&mut self, path: &str, asset_server: &Res<AssetServer>, cache: &mut BitmapCache) {
+        let timeline = read_asset(asset_server, path)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| {
+                ron::de::from_bytes::<Timeline<S>>(&bytes).map_err(|err| err.to_string())
+            });
+        let timeline = match timeline {
+            Ok(timeline) => timeline,
+            Err(err) => {
+                error!("Unable to load cutscene {path}: {err}");
+                return;
+            }
+        };
+
+        self.reset();
+        for cue in &timeline.cues {
+            match cue {
+                Cue::Spawn { image, .. } => {
+                    cache.get_or_create(image, asset_server);
+                }
+                Cue::Sfx(path) | Cue::Dialogue(path) => {
+                    self.sounds
+                        .insert(path.clone(), asset_server.load(path.as_str()));
+                }
+                _ => (),
+            }
+        }
+
+        self.cues = timeline.cues;
+        self.status = Status::Playing;
+    }
+}
+
+impl Anchor {
+    fn resolve(self, (x, y): (f32, f32), size: Vec2, z: f32) -> Vec3 {
+        let origin = match self {
+            Self::TopLeft => Vec2::ZERO,
+            Self::Top => Vec2::new(size.x / 2.0, 0.0),
+            Self::Center => size / 2.0,
+        };
+
+        (origin + Vec2::new(x, y)).extend(z)
+    }
+}
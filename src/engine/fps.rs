@@ -0,0 +1,65 @@
+use crate::engine::{Bitmap, ConfigState, RenderLayer, ScreenSpace};
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+use pix::rgb::Rgba8p;
+
+#[derive(Debug)]
+pub(crate) struct FpsPlugin;
+
+/// Marker for the on-screen frame rate counter.
+#[derive(Component, Debug)]
+struct FpsCounter;
+
+/// How often the counter text is redrawn, in seconds.
+const REFRESH_RATE: f32 = 0.5;
+
+impl Plugin for FpsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(Self::update);
+    }
+}
+
+impl FpsPlugin {
+    /// Show the frame rate in the top-left corner of the screen while it is enabled in the config.
+    fn update(
+        mut commands: Commands,
+        mut timer: Local<Option<Timer>>,
+        mut counter: Query<(Entity, &mut Bitmap), With<FpsCounter>>,
+        config: Res<ConfigState>,
+        diagnostics: Res<Diagnostics>,
+        time: Res<Time>,
+    ) {
+        if !config.show_fps() {
+            if let Ok((entity, _)) = counter.get_single() {
+                commands.entity(entity).despawn();
+            }
+            return;
+        }
+
+        let timer =
+            timer.get_or_insert_with(|| Timer::from_seconds(REFRESH_RATE, TimerMode::Repeating));
+        timer.tick(time.delta());
+
+        let fps = diagnostics
+            .get(FrameTimeDiagnosticsPlugin::FPS)
+            .and_then(|fps| fps.smoothed())
+            .unwrap_or_default();
+        let text = format!("FPS {fps:.0}");
+        let white = Rgba8p::new(1.0, 1.0, 1.0, 1.0);
+
+        match counter.get_single_mut() {
+            Ok((_, mut bitmap)) => {
+                if timer.just_finished() {
+                    *bitmap = Bitmap::from_text(&text, white);
+                }
+            }
+            Err(_) => {
+                let transform = Transform::from_xyz(2.0, 2.0, f32::MAX);
+                let bitmap = Bitmap::from_text(&text, white);
+                commands.spawn((bitmap, transform, ScreenSpace, RenderLayer::Hud, FpsCounter));
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::engine::ConfigState;
+use ahash::HashSet;
+use bevy::{input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug)]
+pub(crate) struct ActionPlugin;
+
+/// Abstract game inputs. Scenes should respond to actions instead of raw key codes so that the
+/// player can rebind keys.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Deserialize, Serialize)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Confirm,
+    Cancel,
+}
+
+/// Maps each [`Action`] to the keys that trigger it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct KeyBindings {
+    map: BTreeMap<Action, Vec<KeyCode>>,
+}
+
+/// The `ActionState` resource is updated once per frame from the keyboard and gamepads.
+#[derive(Debug, Default, Resource)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    any_just_pressed: bool,
+}
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionState>()
+            .add_system_to_stage(CoreStage::PreUpdate, Self::update.after(InputSystem));
+    }
+}
+
+impl ActionPlugin {
+    fn update(
+        mut state: ResMut<ActionState>,
+        config: Res<ConfigState>,
+        keys: Res<Input<KeyCode>>,
+        buttons: Res<Input<GamepadButton>>,
+        mouse: Res<Input<MouseButton>>,
+    ) {
+        let bindings = config.key_bindings();
+
+        state.pressed.clear();
+        state.just_pressed.clear();
+
+        for action in Action::ALL {
+            let bound = bindings.keys(action);
+            let is_bound =
+                |button: &GamepadButton| action.gamepad_buttons().contains(&button.button_type);
+
+            if keys.any_pressed(bound.iter().copied()) || buttons.get_pressed().any(is_bound) {
+                state.pressed.insert(action);
+            }
+            if keys.any_just_pressed(bound.iter().copied())
+                || buttons.get_just_pressed().any(is_bound)
+            {
+                state.just_pressed.insert(action);
+            }
+        }
+
+        state.any_just_pressed = keys.get_just_pressed().next().is_some()
+            || buttons.get_just_pressed().next().is_some()
+            || mouse.get_just_pressed().next().is_some();
+    }
+}
+
+impl Action {
+    pub const ALL: [Self; 6] = [
+        Self::Up,
+        Self::Down,
+        Self::Left,
+        Self::Right,
+        Self::Confirm,
+        Self::Cancel,
+    ];
+
+    /// Gamepad buttons are not configurable.
+    fn gamepad_buttons(&self) -> &'static [GamepadButtonType] {
+        use GamepadButtonType::*;
+
+        match self {
+            Self::Up => &[DPadUp],
+            Self::Down => &[DPadDown],
+            Self::Left => &[DPadLeft],
+            Self::Right => &[DPadRight],
+            Self::Confirm => &[South, Start],
+            Self::Cancel => &[East, Select],
+        }
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        use KeyCode::*;
+
+        let map = BTreeMap::from([
+            (Action::Up, vec![Up, W]),
+            (Action::Down, vec![Down, S]),
+            (Action::Left, vec![Left, A]),
+            (Action::Right, vec![Right, D]),
+            (Action::Confirm, vec![Return, Space, Z]),
+            (Action::Cancel, vec![Back, X]),
+        ]);
+
+        Self { map }
+    }
+}
+
+impl KeyBindings {
+    /// Keys that can't be bound to actions. Escape quits the game.
+    pub const RESERVED: [KeyCode; 3] = [KeyCode::Escape, KeyCode::LWin, KeyCode::RWin];
+
+    /// Get all keys bound to an action.
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.map.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Replace the primary key for an action. The key is unbound from every other action.
+    /// [`KeyBindings::RESERVED`] keys are ignored.
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        if Self::RESERVED.contains(&key) {
+            return;
+        }
+
+        for keys in self.map.values_mut() {
+            keys.retain(|&bound| bound != key);
+        }
+
+        let keys = self.map.entry(action).or_default();
+        if keys.is_empty() {
+            keys.push(key);
+        } else {
+            keys[0] = key;
+        }
+    }
+}
+
+impl ActionState {
+    /// Returns `true` while the action is held.
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    /// Returns `true` on the frame that the action was first pressed.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Returns `true` on any frame where any key or button was pressed, bound or not.
+    pub fn any_just_pressed(&self) -> bool {
+        self.any_just_pressed
+    }
+}
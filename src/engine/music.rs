@@ -0,0 +1,245 @@
+use crate::engine::{ModuleTimeline, MusicChannel, MusicRow};
+use ahash::HashMap;
+use bevy::{ecs::schedule::StateData, prelude::*};
+use bevy_kira_audio::prelude::*;
+use serde::Deserialize;
+use std::{marker::PhantomData, time::Duration};
+
+/// Plays [`Music`] for the state type `S`.
+#[derive(Debug)]
+pub(crate) struct MusicPlugin<S> {
+    marker: PhantomData<S>,
+}
+
+/// The `Music` resource owns the current music track.
+///
+/// Each state can have its own track. When the state changes, the music crossfades to the new
+/// state's track, keeps playing if the track is the same, or fades out if the state has no track.
+///
+/// While an XM module is playing, a [`MusicRow`] event is sent for every pattern row it reaches.
+#[derive(Resource)]
+pub struct Music<S> {
+    tracks: HashMap<S, Track>,
+    current: Option<(Track, Handle<AudioInstance>)>,
+    timeline: Option<Handle<ModuleTimeline>>,
+    position: Option<f64>,
+    requests: Vec<Request>,
+    crossfade: f32,
+}
+
+/// A music track.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Track {
+    /// Asset path of the audio file.
+    pub path: String,
+    /// Position in seconds where the loop begins. Everything before it is an intro that is only
+    /// played once. When this is `None`, modules loop from their restart position and other tracks
+    /// loop as a whole.
+    #[serde(default)]
+    pub loop_from: Option<f64>,
+}
+
+#[derive(Debug)]
+enum Request {
+    Play(Track),
+    Stop,
+    Pause,
+    Resume,
+}
+
+/// Default crossfade duration in seconds.
+const CROSSFADE: f32 = 1.0;
+
+impl<S> Default for MusicPlugin<S> {
+    fn default() -> Self {
+        Self {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<S: StateData> Plugin for MusicPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Music::<S>::default())
+            .add_system(Self::update)
+            .add_system(Self::rows.after(Self::update));
+    }
+}
+
+impl<S: StateData> MusicPlugin<S> {
+    fn update(
+        mut music: ResMut<Music<S>>,
+        mut instances: ResMut<Assets<AudioInstance>>,
+        state: Res<State<S>>,
+        asset_server: Res<AssetServer>,
+        channel: Res<AudioChannel<MusicChannel>>,
+    ) {
+        // Switch to the track for the new state.
+        if state.is_changed() {
+            let request = match music.tracks.get(state.current()) {
+                Some(track) => Request::Play(track.clone()),
+                None => Request::Stop,
+            };
+            music.requests.insert(0, request);
+        }
+
+        let tween = AudioTween::linear(Duration::from_secs_f32(music.crossfade));
+        for request in std::mem::take(&mut music.requests) {
+            match request {
+                Request::Play(track) => {
+                    if matches!(&music.current, Some((current, _)) if *current == track) {
+                        continue;
+                    }
+
+                    if let Some((_, handle)) = music.current.take() {
+                        if let Some(instance) = instances.get_mut(&handle) {
+                            instance.stop(tween.clone());
+                        }
+                    }
+
+                    let module = track.path.ends_with(".xm");
+                    let mut command = channel.play(asset_server.load(track.path.as_str()));
+                    match track.loop_from {
+                        Some(start) => {
+                            command.loop_from(start);
+                        }
+                        // Modules already loop from their restart position.
+                        None if module => {}
+                        None => {
+                            command.looped();
+                        }
+                    }
+                    let handle = command.fade_in(tween.clone()).handle();
+
+                    // Modules have a timeline for sending row events.
+                    music.timeline = module
+                        .then(|| asset_server.load(format!("{}#timeline", track.path).as_str()));
+                    music.position = None;
+                    music.current = Some((track, handle));
+                }
+                Request::Stop => {
+                    music.timeline = None;
+                    if let Some((_, handle)) = music.current.take() {
+                        if let Some(instance) = instances.get_mut(&handle) {
+                            instance.stop(tween.clone());
+                        }
+                    }
+                }
+                Request::Pause | Request::Resume => {
+                    let Some((_, handle)) = &music.current else {
+                        continue;
+                    };
+                    if let Some(instance) = instances.get_mut(handle) {
+                        if matches!(request, Request::Pause) {
+                            instance.pause(tween.clone());
+                        } else {
+                            instance.resume(tween.clone());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send [`MusicRow`] events for the rows played since the last frame.
+    fn rows(
+        mut music: ResMut<Music<S>>,
+        mut events: EventWriter<MusicRow>,
+        timelines: Res<Assets<ModuleTimeline>>,
+        channel: Res<AudioChannel<MusicChannel>>,
+    ) {
+        let Some(timeline) = music
+            .timeline
+            .as_ref()
+            .and_then(|handle| timelines.get(handle))
+        else {
+            return;
+        };
+        let Some((track, handle)) = &music.current else {
+            return;
+        };
+        let Some(position) = channel.state(handle).position() else {
+            return;
+        };
+        let loop_from = track.loop_from.unwrap_or_else(|| timeline.loop_start());
+
+        let previous = music.position.replace(position).unwrap_or_default();
+        if position < previous {
+            // The track looped.
+            for row in timeline.rows_between(previous, f64::INFINITY) {
+                events.send(*row);
+            }
+            for row in timeline.rows_between(loop_from, position) {
+                events.send(*row);
+            }
+        } else {
+            for row in timeline.rows_between(previous, position) {
+                events.send(*row);
+            }
+        }
+    }
+}
+
+impl<S> Default for Music<S> {
+    fn default() -> Self {
+        Self {
+            tracks: HashMap::default(),
+            current: None,
+            timeline: None,
+            position: None,
+            requests: Vec::new(),
+            crossfade: CROSSFADE,
+        }
+    }
+}
+
+impl<S: StateData> Music<S> {
+    /// Set the track that plays while in the given state.
+    pub fn set_track(&mut self, state: S, track: Track) {
+        self.tracks.insert(state, track);
+    }
+
+    /// Crossfade to a track, regardless of the current state. Playing the current track again does
+    /// nothing.
+    pub fn play(&mut self, track: Track) {
+        self.requests.push(Request::Play(track));
+    }
+
+    /// Fade out the current track.
+    pub fn stop(&mut self) {
+        self.requests.push(Request::Stop);
+    }
+
+    /// Pause the current track, e.g. when the game is paused.
+    pub fn pause(&mut self) {
+        self.requests.push(Request::Pause);
+    }
+
+    /// Resume the current track after [`Music::pause`].
+    pub fn resume(&mut self) {
+        self.requests.push(Request::Resume);
+    }
+
+    /// Set the crossfade duration in seconds.
+    pub fn set_crossfade(&mut self, seconds: f32) {
+        self.crossfade = seconds.max(0.0);
+    }
+}
+
+impl Track {
+    /// A track that loops from the beginning.
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            loop_from: None,
+        }
+    }
+
+    /// A track with an intro that plays once before looping from the given position in seconds.
+    pub fn with_intro(path: &str, loop_from: f64) -> Self {
+        Self {
+            path: path.to_string(),
+            loop_from: Some(loop_from),
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use crate::engine::{Camera, ConfigState, Filter, Scaling};
+use bevy::prelude::*;
+use bevy_pixels::prelude::*;
+use pix::{rgb::Rgba8p, Raster};
+use std::f32::consts::PI;
+
+#[derive(Debug)]
+pub(crate) struct OutputPlugin;
+
+/// The `Output` resource describes how the [`Camera`] raster is scaled into the window.
+///
+/// With [`Scaling::Integer`] and no [`Filter`], the pixel buffer has the same size as the screen
+/// and `Pixels` does the scaling. Otherwise the pixel buffer is sized for the window and the screen
+/// is scaled into it on the CPU, where the post-processing filter is applied at the output scale.
+#[derive(Debug, Default, Resource)]
+pub struct Output {
+    /// Scaling policy.
+    scaling: Scaling,
+    /// Size of the window.
+    window: UVec2,
+    /// Size of the screen.
+    screen: UVec2,
+    /// Size of the pixel buffer.
+    buffer: UVec2,
+    /// Position of the screen within the pixel buffer.
+    content_pos: UVec2,
+    /// Size of the screen within the pixel buffer.
+    content_size: UVec2,
+    /// Post-processing filter.
+    filter: Filter,
+    /// Source column for each column of the screen within the pixel buffer.
+    columns: Vec<usize>,
+    /// Source row for each row of the screen within the pixel buffer.
+    rows: Vec<usize>,
+    /// Brightness of each column of the screen within the pixel buffer.
+    column_gain: Vec<u8>,
+    /// Brightness of each row of the screen within the pixel buffer.
+    row_gain: Vec<u8>,
+    /// Source pixel for each pixel of the screen within the pixel buffer, for curved glass.
+    curve: Vec<Option<usize>>,
+}
+
+/// Opaque black, used for the letterbox.
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+/// Brightness of the gaps between rows of pixels for the scanline and CRT filters.
+const SCANLINE_GAIN: f32 = 0.55;
+
+/// Brightness of the grid between pixels for the LCD filter.
+const LCD_GAIN: f32 = 0.7;
+
+/// Amount of barrel distortion for the CRT filter.
+const CRT_CURVATURE: f32 = 0.04;
+
+/// How much of each pixel's left neighbor bleeds into it with the CRT filter, out of 256.
+const CRT_BLEED: u16 = 80;
+
+impl Plugin for OutputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Output>()
+            .add_system_to_stage(CoreStage::PostUpdate, Self::resize);
+    }
+}
+
+impl OutputPlugin {
+    /// Resize the pixel buffer when the window, screen resolution, scaling policy, or filter
+    /// changes.
+    fn resize(
+        mut output: ResMut<Output>,
+        mut pixels_res: ResMut<PixelsResource>,
+        camera: Res<Camera>,
+        config: Res<ConfigState>,
+        windows: Res<Windows>,
+    ) {
+        let Some(window) = windows.get_primary() else {
+            return;
+        };
+
+        let window = UVec2::new(window.physical_width(), window.physical_height()).max(UVec2::ONE);
+        let raster = camera.raster();
+        let screen = UVec2::new(raster.width(), raster.height());
+        let (scaling, filter) = (config.scaling(), config.filter());
+        if output.scaling == scaling
+            && output.filter == filter
+            && output.screen == screen
+            && output.window == window
+        {
+            return;
+        }
+
+        let next = Output::new(scaling, filter, screen, window);
+
+        if next.buffer != output.buffer {
+            if let Err(err) = pixels_res
+                .pixels
+                .resize_buffer(next.buffer.x, next.buffer.y)
+            {
+                error!("Unable to resize pixel buffer: {err}");
+                return;
+            }
+        }
+
+        *output = next;
+    }
+}
+
+impl Output {
+    fn new(scaling: Scaling, filter: Filter, screen: UVec2, window: UVec2) -> Self {
+        let (buffer, content_pos, content_size) = match scaling {
+            Scaling::Integer if filter == Filter::None => (screen, UVec2::ZERO, screen),
+            Scaling::Integer => {
+                // `Pixels` adds the letterbox, as long as the buffer is an integer multiple.
+                let scale = (window / screen).min_element().max(1);
+                let size = screen * scale;
+
+                (size, UVec2::ZERO, size)
+            }
+            Scaling::Fit => {
+                let scale = (window.as_vec2() / screen.as_vec2()).min_element();
+                let size = (screen.as_vec2() * scale).round().as_uvec2();
+                let size = size.clamp(UVec2::ONE, window);
+
+                (window, (window - size) / 2, size)
+            }
+            Scaling::Stretch => (window, UVec2::ZERO, window),
+        };
+
+        let columns = source_indices(screen.x, content_size.x);
+        let rows = source_indices(screen.y, content_size.y);
+        let cell = content_size.as_vec2() / screen.as_vec2();
+
+        // Gaps are only drawn when there are enough output pixels per screen pixel to see them.
+        let column_gain = (0..content_size.x)
+            .map(|x| match filter {
+                Filter::Lcd if cell.x >= 3.0 && is_cell_edge(x, screen.x, content_size.x) => {
+                    to_gain(LCD_GAIN)
+                }
+                _ => u8::MAX,
+            })
+            .collect();
+        let row_gain = (0..content_size.y)
+            .map(|y| {
+                let t = cell_fraction(y, screen.y, content_size.y);
+                match filter {
+                    Filter::Scanlines if cell.y >= 2.0 && t >= 0.5 => to_gain(SCANLINE_GAIN),
+                    Filter::Crt if cell.y >= 2.0 => {
+                        to_gain(1.0 - (1.0 - SCANLINE_GAIN) * (PI * t).sin().powi(2))
+                    }
+                    Filter::Lcd if cell.y >= 3.0 && is_cell_edge(y, screen.y, content_size.y) => {
+                        to_gain(LCD_GAIN)
+                    }
+                    _ => u8::MAX,
+                }
+            })
+            .collect();
+
+        let curve = if filter == Filter::Crt {
+            barrel(screen, content_size)
+        } else {
+            Vec::new()
+        };
+
+        Self {
+            scaling,
+            window,
+            screen,
+            buffer,
+            content_pos,
+            content_size,
+            filter,
+            columns,
+            rows,
+            column_gain,
+            row_gain,
+            curve,
+        }
+    }
+
+    /// Convert a position in the window to a position on the screen, accounting for the scaling
+    /// and letterbox. Returns `None` if the position is outside of the screen.
+    ///
+    /// The position uses Bevy's window coordinates, e.g. from [`Window::cursor_position`]: logical
+    /// pixels with the origin in the bottom-left corner.
+    pub fn window_to_screen(&self, window: &Window, position: Vec2) -> Option<Vec2> {
+        if self.buffer == UVec2::ZERO {
+            return None;
+        }
+
+        let size = Vec2::new(
+            window.physical_width() as f32,
+            window.physical_height() as f32,
+        );
+        let physical = position * window.scale_factor() as f32;
+        let physical = Vec2::new(physical.x, size.y - physical.y);
+
+        // `Pixels` scales the buffer by the largest integer that fits, and centers it.
+        let buffer = self.buffer.as_vec2();
+        let scale = (size / buffer).min_element().max(1.0).floor();
+        let offset = (size - buffer * scale) / 2.0;
+        let buffer_pos = (physical - offset) / scale;
+
+        // Then the screen is scaled into the buffer.
+        let content = buffer_pos - self.content_pos.as_vec2();
+        let screen = content * self.screen.as_vec2() / self.content_size.as_vec2();
+
+        let inside = screen.cmpge(Vec2::ZERO).all() && screen.cmplt(self.screen.as_vec2()).all();
+        inside.then_some(screen)
+    }
+
+    /// Copy the screen into the pixel buffer, scaling it, applying the filter, and filling the
+    /// letterbox.
+    pub(crate) fn present(&self, raster: &Raster<Rgba8p>, frame: &mut [u8]) {
+        let src = raster.as_u8_slice();
+        let screen = UVec2::new(raster.width(), raster.height());
+
+        // Fast path when `Pixels` does all of the scaling.
+        if self.buffer == screen && self.content_size == screen && frame.len() == src.len() {
+            frame.copy_from_slice(src);
+            return;
+        }
+
+        // The pixel buffer or the screen has not been resized yet.
+        let content_width = self.content_size.x as usize;
+        if self.screen != screen || frame.len() != (self.buffer.x * self.buffer.y * 4) as usize {
+            return;
+        }
+
+        let stride = self.buffer.x as usize * 4;
+        let src_stride = screen.x as usize * 4;
+        let left = self.content_pos.x as usize * 4;
+        let right = left + content_width * 4;
+        let top = self.content_pos.y as usize;
+        let bottom = top + self.content_size.y as usize;
+
+        for (y, row) in frame.chunks_exact_mut(stride).enumerate() {
+            if y < top || y >= bottom {
+                fill(row, BLACK);
+                continue;
+            }
+
+            fill(&mut row[..left], BLACK);
+            fill(&mut row[right..], BLACK);
+
+            let content_y = y - top;
+            let row_gain = self.row_gain[content_y];
+            let dst_row = row[left..right].chunks_exact_mut(4);
+
+            if self.filter == Filter::Crt {
+                let curve = &self.curve[content_y * content_width..][..content_width];
+                for (dst, &index) in dst_row.zip(curve) {
+                    match index {
+                        Some(index) => {
+                            let pixel = bleed(src, index, src_stride);
+                            dst.copy_from_slice(&modulate(pixel, row_gain));
+                        }
+                        None => dst.copy_from_slice(&BLACK),
+                    }
+                }
+                continue;
+            }
+
+            let src_row = &src[self.rows[content_y] * src_stride..][..src_stride];
+            for ((dst, &x), &column_gain) in dst_row.zip(&self.columns).zip(&self.column_gain) {
+                let pixel = src_row[x * 4..x * 4 + 4].try_into().unwrap();
+
+                dst.copy_from_slice(&modulate(pixel, row_gain.min(column_gain)));
+            }
+        }
+    }
+}
+
+/// Map each output index onto the source index it samples.
+fn source_indices(source: u32, output: u32) -> Vec<usize> {
+    (0..output)
+        .map(|i| (i as u64 * source as u64 / output as u64) as usize)
+        .collect()
+}
+
+/// How far through its source pixel an output index lies, in `[0, 1)`.
+fn cell_fraction(i: u32, source: u32, output: u32) -> f32 {
+    ((i as u64 * source as u64) % output as u64) as f32 / output as f32
+}
+
+/// Returns `true` if the output index is the last one that samples its source pixel.
+fn is_cell_edge(i: u32, source: u32, output: u32) -> bool {
+    let index = |i: u32| i as u64 * source as u64 / output as u64;
+
+    i + 1 == output || index(i) != index(i + 1)
+}
+
+fn to_gain(gain: f32) -> u8 {
+    (gain.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Build the source pixel lookup for curved glass. Pixels outside the curved screen are `None`.
+fn barrel(screen: UVec2, content_size: UVec2) -> Vec<Option<usize>> {
+    let size = content_size.as_vec2();
+
+    (0..content_size.y)
+        .flat_map(|y| (0..content_size.x).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            // Normalized coordinates in `[-1, 1]`
+            let pos = (Vec2::new(x as f32, y as f32) + 0.5) / size * 2.0 - 1.0;
+            let pos = pos * (1.0 + CRT_CURVATURE * Vec2::new(pos.y * pos.y, pos.x * pos.x));
+            if pos.abs().max_element() >= 1.0 {
+                return None;
+            }
+
+            let src = ((pos + 1.0) / 2.0 * screen.as_vec2()).as_uvec2();
+            let src = src.min(screen - 1);
+
+            Some((src.y * screen.x + src.x) as usize)
+        })
+        .collect()
+}
+
+/// Blend a source pixel with its left neighbor.
+fn bleed(src: &[u8], index: usize, src_stride: usize) -> [u8; 4] {
+    let offset = index * 4;
+    let pixel = &src[offset..offset + 4];
+    if offset.is_multiple_of(src_stride) {
+        return pixel.try_into().unwrap();
+    }
+
+    let neighbor = &src[offset - 4..offset];
+    let mut out = [0; 4];
+    for (out, (&a, &b)) in out.iter_mut().zip(pixel.iter().zip(neighbor)) {
+        *out = ((a as u16 * (256 - CRT_BLEED) + b as u16 * CRT_BLEED) >> 8) as u8;
+    }
+
+    out
+}
+
+/// Scale the color channels of a pixel by `gain / 255`. Alpha is unchanged.
+fn modulate(mut pixel: [u8; 4], gain: u8) -> [u8; 4] {
+    if gain < u8::MAX {
+        for chan in &mut pixel[..3] {
+            *chan = (*chan as u16 * gain as u16 / 255) as u8;
+        }
+    }
+
+    pixel
+}
+
+/// Fill every pixel in a row of RGBA bytes with the same color.
+fn fill(row: &mut [u8], color: [u8; 4]) {
+    for pixel in row.chunks_exact_mut(4) {
+        pixel.copy_from_slice(&color);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::engine::ConfigState;
+use bevy::prelude::*;
+use std::{
+    ops::Range,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug)]
+pub(crate) struct RngPlugin;
+
+/// The `Rng` resource is the only source of randomness in the game.
+///
+/// It is seeded once (from a replay file, the command line, the `SEED` env var, or the config file,
+/// otherwise at random) and provides an independent sub-stream for each [`RngStream`]. Drawing
+/// numbers from one stream never affects the sequence produced by any other stream, so cosmetic
+/// randomness cannot perturb gameplay.
+#[derive(Debug, Resource)]
+pub struct Rng {
+    seed: u64,
+    streams: [RngSource; RngStream::COUNT],
+}
+
+/// Identifies a random number sub-stream.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum RngStream {
+    /// Anything that affects the outcome of the game.
+    Gameplay,
+    /// Enemy and item spawns.
+    Spawns,
+    /// Purely cosmetic particle effects.
+    Particles,
+    /// Sound effect variation.
+    Audio,
+}
+
+/// A small, fast pseudorandom number generator (SplitMix64).
+#[derive(Clone, Debug)]
+pub struct RngSource {
+    state: u64,
+}
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        let config = app.world.resource::<ConfigState>();
+        let seed = config.seed().unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+
+        info!("RNG seed: {seed}");
+
+        app.insert_resource(Rng::new(seed));
+    }
+}
+
+impl Rng {
+    /// Create a new `Rng` with the given seed.
+    pub fn new(seed: u64) -> Self {
+        let streams = RngStream::ALL.map(|stream| stream.source(seed));
+
+        Self { seed, streams }
+    }
+
+    /// Get the seed that all streams were derived from.
+    ///
+    /// Record this in a replay file to reproduce the same sequence of random numbers.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Reset all streams to the beginning of the sequence for the given seed.
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::new(seed);
+    }
+
+    /// Get a mutable reference to a sub-stream.
+    pub fn stream(&mut self, stream: RngStream) -> &mut RngSource {
+        &mut self.streams[stream as usize]
+    }
+}
+
+impl RngStream {
+    const COUNT: usize = 4;
+    const ALL: [Self; Self::COUNT] = [Self::Gameplay, Self::Spawns, Self::Particles, Self::Audio];
+
+    /// Derive the initial state for this stream from the master seed.
+    fn source(self, seed: u64) -> RngSource {
+        let mut source =
+            RngSource::new(seed ^ (self as u64 + 1).wrapping_mul(0xa076_1d64_78bd_642f));
+
+        // Discard the first output so that similar seeds diverge immediately.
+        source.u64();
+
+        source
+    }
+}
+
+impl RngSource {
+    /// Create a new standalone generator with the given seed.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Generate a uniformly distributed `u64`.
+    pub fn u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        z ^ (z >> 31)
+    }
+
+    /// Generate a uniformly distributed `u32`.
+    pub fn u32(&mut self) -> u32 {
+        (self.u64() >> 32) as u32
+    }
+
+    /// Generate a uniformly distributed `f32` in the range `[0, 1)`.
+    pub fn f32(&mut self) -> f32 {
+        (self.u64() >> 40) as f32 / (1_u64 << 24) as f32
+    }
+
+    /// Generate a uniformly distributed `f32` in the given range.
+    pub fn range_f32(&mut self, range: Range<f32>) -> f32 {
+        range.start + (range.end - range.start) * self.f32()
+    }
+
+    /// Generate a uniformly distributed `i32` in the given range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty.
+    pub fn range_i32(&mut self, range: Range<i32>) -> i32 {
+        assert!(!range.is_empty(), "Empty range");
+
+        let span = range.end.abs_diff(range.start) as u64;
+
+        range.start.wrapping_add((self.u64() % span) as i32)
+    }
+
+    /// Returns `true` with the given probability.
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.f32() < probability
+    }
+
+    /// Choose a random element from a slice.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            None
+        } else {
+            Some(&items[(self.u64() % items.len() as u64) as usize])
+        }
+    }
+
+    /// Fork a new independent generator from this one.
+    pub fn fork(&mut self) -> Self {
+        Self::new(self.u64())
+    }
+}
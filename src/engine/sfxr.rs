@@ -0,0 +1,553 @@
+use crate::engine::{Rng, RngSource, RngStream};
+use ahash::HashMap;
+use bevy::{
+    asset::{AssetLoader, Error, LoadContext, LoadedAsset},
+    ecs::system::SystemParam,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use bevy_kira_audio::AudioSource;
+use kira::{
+    dsp::Frame,
+    sound::static_sound::{StaticSoundData, StaticSoundSettings},
+};
+use serde::Deserialize;
+use std::f32::consts::TAU;
+
+#[derive(Debug)]
+pub(crate) struct SfxrPlugin;
+
+/// A bank of named sound effects, loaded from a JSON object that maps names to jsfxr parameter
+/// sets.
+///
+/// Each sound is synthesized when the bank is loaded and can be played with its label, e.g.
+/// `sfx/blips.sfxr.json#blip1`. Use [`Sfxr`] to play a random variation instead.
+#[derive(Debug, Deserialize, TypeUuid)]
+#[serde(transparent)]
+#[uuid = "3f5c5d0e-5a3b-4f0e-8a49-2c3d8c1b7e61"]
+pub struct SfxrBank {
+    sounds: HashMap<String, SfxrParams>,
+}
+
+/// Parameters for the sfxr synthesizer, using the field names from jsfxr's JSON export.
+///
+/// Unsigned parameters are in the range `[0, 1]` and signed parameters are in `[-1, 1]`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SfxrParams {
+    /// 0: square, 1: sawtooth, 2: sine, 3: noise.
+    pub wave_type: u8,
+    #[serde(rename = "p_env_attack")]
+    pub attack: f32,
+    #[serde(rename = "p_env_sustain")]
+    pub sustain: f32,
+    #[serde(rename = "p_env_punch")]
+    pub punch: f32,
+    #[serde(rename = "p_env_decay")]
+    pub decay: f32,
+    #[serde(rename = "p_base_freq")]
+    pub base_freq: f32,
+    #[serde(rename = "p_freq_limit")]
+    pub freq_limit: f32,
+    /// Signed.
+    #[serde(rename = "p_freq_ramp")]
+    pub freq_ramp: f32,
+    /// Signed.
+    #[serde(rename = "p_freq_dramp")]
+    pub freq_dramp: f32,
+    #[serde(rename = "p_vib_strength")]
+    pub vib_strength: f32,
+    #[serde(rename = "p_vib_speed")]
+    pub vib_speed: f32,
+    /// Signed.
+    #[serde(rename = "p_arp_mod")]
+    pub arp_mod: f32,
+    #[serde(rename = "p_arp_speed")]
+    pub arp_speed: f32,
+    #[serde(rename = "p_duty")]
+    pub duty: f32,
+    /// Signed.
+    #[serde(rename = "p_duty_ramp")]
+    pub duty_ramp: f32,
+    #[serde(rename = "p_repeat_speed")]
+    pub repeat_speed: f32,
+    /// Signed.
+    #[serde(rename = "p_pha_offset")]
+    pub pha_offset: f32,
+    /// Signed.
+    #[serde(rename = "p_pha_ramp")]
+    pub pha_ramp: f32,
+    #[serde(rename = "p_lpf_freq")]
+    pub lpf_freq: f32,
+    /// Signed.
+    #[serde(rename = "p_lpf_ramp")]
+    pub lpf_ramp: f32,
+    #[serde(rename = "p_lpf_resonance")]
+    pub lpf_resonance: f32,
+    #[serde(rename = "p_hpf_freq")]
+    pub hpf_freq: f32,
+    /// Signed.
+    #[serde(rename = "p_hpf_ramp")]
+    pub hpf_ramp: f32,
+    pub sound_vol: f32,
+}
+
+/// Plays random variations of the sounds in an [`SfxrBank`].
+#[derive(SystemParam)]
+pub struct Sfxr<'w, 's> {
+    banks: Res<'w, Assets<SfxrBank>>,
+    sources: ResMut<'w, Assets<AudioSource>>,
+    rng: ResMut<'w, Rng>,
+    asset_server: Res<'w, AssetServer>,
+    variants: ResMut<'w, SfxrVariants>,
+    handles: Local<'s, HashMap<String, Handle<SfxrBank>>>,
+}
+
+/// The `SfxrVariants` resource keeps the variations synthesized by [`Sfxr`], so sounds that are
+/// played often are only synthesized a few times.
+///
+/// The [`SfxPlayer`](crate::engine::SfxPlayer) counts the variations of a sound as that sound, so
+/// they share its voice limit and triggers of different variations are merged.
+#[derive(Debug, Default, Resource)]
+pub struct SfxrVariants {
+    pools: HashMap<String, Vec<Handle<AudioSource>>>,
+    sounds: HashMap<Handle<AudioSource>, Handle<AudioSource>>,
+}
+
+#[derive(Debug, Default)]
+struct SfxrLoader;
+
+/// Output sample rate of synthesized sounds. jsfxr's `sample_rate` and `sample_size` export
+/// settings are ignored.
+const SAMPLE_RATE: u32 = 44_100;
+
+/// Number of variations synthesized for each sound. After that, [`Sfxr::mutated`] picks one of
+/// them at random.
+const VARIATIONS: usize = 8;
+
+impl Plugin for SfxrPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<SfxrBank>()
+            .add_asset_loader(SfxrLoader)
+            .init_resource::<SfxrVariants>();
+    }
+}
+
+impl AssetLoader for SfxrLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let bank = serde_json::from_slice::<SfxrBank>(bytes)?;
+
+            // Noise is seeded the same way every time, so loaded sounds are reproducible.
+            let mut rng = RngSource::new(0);
+            for (name, params) in &bank.sounds {
+                let sound = params.to_audio_source(&mut rng);
+                load_context.set_labeled_asset(name, LoadedAsset::new(sound));
+            }
+            load_context.set_default_asset(LoadedAsset::new(bank));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sfxr.json"]
+    }
+}
+
+impl SfxrBank {
+    /// Get the parameters for a sound by name.
+    pub fn get(&self, name: &str) -> Option<&SfxrParams> {
+        self.sounds.get(name)
+    }
+}
+
+impl<'w, 's> Sfxr<'w, 's> {
+    /// Synthesize a random variation of a sound from a bank, like the mutate button in sfxr.
+    /// `amount` is the largest change made to any parameter, e.g. `0.05`.
+    ///
+    /// The path includes the sound label, e.g. `sfx/blips.sfxr.json#blip1`. The unmodified sound
+    /// is returned while the bank is still loading. Only a few variations of each sound are
+    /// synthesized, and reused once there are enough of them.
+    pub fn mutated(&mut self, path: &str, amount: f32) -> Handle<AudioSource> {
+        let Some((bank, name)) = path.split_once('#') else {
+            return self.asset_server.load(path);
+        };
+
+        let variants = &mut *self.variants;
+        let pool = variants.pools.entry(path.to_string()).or_default();
+        if pool.len() >= VARIATIONS {
+            if let Some(variant) = self.rng.stream(RngStream::Audio).choose(pool) {
+                return variant.clone();
+            }
+        }
+
+        let asset_server = &self.asset_server;
+        let handle = self
+            .handles
+            .entry(bank.to_string())
+            .or_insert_with(|| asset_server.load(bank));
+        let Some(params) = self.banks.get(handle).and_then(|bank| bank.get(name)) else {
+            return self.asset_server.load(path);
+        };
+
+        let rng = self.rng.stream(RngStream::Audio);
+        let sound = params.mutated(amount, rng).to_audio_source(rng);
+        let variant = self.sources.add(sound);
+
+        pool.push(variant.clone());
+        variants
+            .sounds
+            .insert(variant.clone(), self.asset_server.load(path));
+
+        variant
+    }
+}
+
+impl SfxrVariants {
+    /// Get the sound that a variation was synthesized from.
+    pub(crate) fn sound_of(&self, variant: &Handle<AudioSource>) -> Option<&Handle<AudioSource>> {
+        self.sounds.get(variant)
+    }
+}
+
+impl Default for SfxrParams {
+    /// The default sfxr sound.
+    fn default() -> Self {
+        Self {
+            wave_type: 0,
+            attack: 0.0,
+            sustain: 0.3,
+            punch: 0.0,
+            decay: 0.4,
+            base_freq: 0.3,
+            freq_limit: 0.0,
+            freq_ramp: 0.0,
+            freq_dramp: 0.0,
+            vib_strength: 0.0,
+            vib_speed: 0.0,
+            arp_mod: 0.0,
+            arp_speed: 0.0,
+            duty: 0.0,
+            duty_ramp: 0.0,
+            repeat_speed: 0.0,
+            pha_offset: 0.0,
+            pha_ramp: 0.0,
+            lpf_freq: 1.0,
+            lpf_ramp: 0.0,
+            lpf_resonance: 0.0,
+            hpf_freq: 0.0,
+            hpf_ramp: 0.0,
+            sound_vol: 0.5,
+        }
+    }
+}
+
+impl SfxrParams {
+    /// A copy where each parameter has a 50% chance of changing by up to `amount`.
+    pub fn mutated(&self, amount: f32, rng: &mut RngSource) -> Self {
+        let mut params = self.clone();
+        let mut mutate = |value: &mut f32, min: f32| {
+            if rng.chance(0.5) {
+                *value = (*value + rng.range_f32(-amount..amount)).clamp(min, 1.0);
+            }
+        };
+
+        mutate(&mut params.base_freq, 0.0);
+        mutate(&mut params.freq_ramp, -1.0);
+        mutate(&mut params.freq_dramp, -1.0);
+        mutate(&mut params.duty, 0.0);
+        mutate(&mut params.duty_ramp, -1.0);
+        mutate(&mut params.vib_strength, 0.0);
+        mutate(&mut params.vib_speed, 0.0);
+        mutate(&mut params.attack, 0.0);
+        mutate(&mut params.sustain, 0.0);
+        mutate(&mut params.decay, 0.0);
+        mutate(&mut params.punch, 0.0);
+        mutate(&mut params.lpf_resonance, 0.0);
+        mutate(&mut params.lpf_freq, 0.0);
+        mutate(&mut params.lpf_ramp, -1.0);
+        mutate(&mut params.hpf_freq, 0.0);
+        mutate(&mut params.hpf_ramp, -1.0);
+        mutate(&mut params.pha_offset, -1.0);
+        mutate(&mut params.pha_ramp, -1.0);
+        mutate(&mut params.repeat_speed, 0.0);
+        mutate(&mut params.arp_speed, 0.0);
+        mutate(&mut params.arp_mod, -1.0);
+
+        params
+    }
+
+    /// Synthesize the sound. The random number generator is only used for noise.
+    pub fn synthesize(&self, rng: &mut RngSource) -> Vec<f32> {
+        Synth::new(self, rng).collect()
+    }
+
+    fn to_audio_source(&self, rng: &mut RngSource) -> AudioSource {
+        let frames = self
+            .synthesize(rng)
+            .into_iter()
+            .map(|sample| Frame {
+                left: sample,
+                right: sample,
+            })
+            .collect::<Vec<_>>();
+
+        AudioSource {
+            sound: StaticSoundData {
+                sample_rate: SAMPLE_RATE,
+                frames: frames.into(),
+                settings: StaticSoundSettings::default(),
+            },
+        }
+    }
+}
+
+/// The sfxr synthesizer, ported from DrPetter's original. Produces one sample per iteration.
+struct Synth<'a> {
+    params: &'a SfxrParams,
+    rng: &'a mut RngSource,
+    playing: bool,
+    gain: f32,
+
+    phase: usize,
+    period: f64,
+    max_period: f64,
+    slide: f64,
+    delta_slide: f64,
+    square_duty: f32,
+    square_slide: f32,
+    arp_mod: f64,
+    arp_time: usize,
+    arp_limit: usize,
+
+    lpf_pos: f32,
+    lpf_delta: f32,
+    lpf_cutoff: f32,
+    lpf_cutoff_delta: f32,
+    lpf_damping: f32,
+    hpf_pos: f32,
+    hpf_cutoff: f32,
+    hpf_cutoff_delta: f32,
+
+    vib_phase: f32,
+    vib_speed: f32,
+    vib_amp: f32,
+
+    env_volume: f32,
+    env_stage: usize,
+    env_time: usize,
+    env_length: [usize; 3],
+
+    phaser_phase: f32,
+    phaser_delta: f32,
+    phaser_buffer: [f32; 1024],
+    phaser_pos: usize,
+    noise_buffer: [f32; 32],
+
+    repeat_time: usize,
+    repeat_limit: usize,
+}
+
+impl<'a> Synth<'a> {
+    fn new(params: &'a SfxrParams, rng: &'a mut RngSource) -> Self {
+        let lpf_cutoff = params.lpf_freq.powi(3) * 0.1;
+        let mut synth = Self {
+            params,
+            rng,
+            playing: true,
+            gain: params.sound_vol.exp() - 1.0,
+            phase: 0,
+            period: 0.0,
+            max_period: 0.0,
+            slide: 0.0,
+            delta_slide: 0.0,
+            square_duty: 0.0,
+            square_slide: 0.0,
+            arp_mod: 0.0,
+            arp_time: 0,
+            arp_limit: 0,
+            lpf_pos: 0.0,
+            lpf_delta: 0.0,
+            lpf_cutoff,
+            lpf_cutoff_delta: 1.0 + params.lpf_ramp * 0.0001,
+            lpf_damping: (5.0 / (1.0 + params.lpf_resonance.powi(2) * 20.0) * (0.01 + lpf_cutoff))
+                .min(0.8),
+            hpf_pos: 0.0,
+            hpf_cutoff: params.hpf_freq.powi(2) * 0.1,
+            hpf_cutoff_delta: 1.0 + params.hpf_ramp * 0.0003,
+            vib_phase: 0.0,
+            vib_speed: params.vib_speed.powi(2) * 0.01,
+            vib_amp: params.vib_strength * 0.5,
+            env_volume: 0.0,
+            env_stage: 0,
+            env_time: 0,
+            env_length: [
+                (params.attack.powi(2) * 100_000.0) as usize,
+                (params.sustain.powi(2) * 100_000.0) as usize,
+                (params.decay.powi(2) * 100_000.0) as usize,
+            ],
+            phaser_phase: params.pha_offset.powi(2) * 1020.0 * params.pha_offset.signum(),
+            phaser_delta: params.pha_ramp.powi(2) * params.pha_ramp.signum(),
+            phaser_buffer: [0.0; 1024],
+            phaser_pos: 0,
+            noise_buffer: [0.0; 32],
+            repeat_time: 0,
+            repeat_limit: if params.repeat_speed == 0.0 {
+                0
+            } else {
+                ((1.0 - params.repeat_speed).powi(2) * 20_000.0 + 32.0) as usize
+            },
+        };
+        synth.refill_noise();
+        synth.reset_frequency();
+
+        synth
+    }
+
+    /// Reset the pitch, which also happens each time the sound repeats.
+    fn reset_frequency(&mut self) {
+        let params = self.params;
+        self.period = 100.0 / (params.base_freq as f64 * params.base_freq as f64 + 0.001);
+        self.max_period = 100.0 / (params.freq_limit as f64 * params.freq_limit as f64 + 0.001);
+        self.slide = 1.0 - (params.freq_ramp as f64).powi(3) * 0.01;
+        self.delta_slide = -(params.freq_dramp as f64).powi(3) * 0.000_001;
+        self.square_duty = 0.5 - params.duty * 0.5;
+        self.square_slide = -params.duty_ramp * 0.000_05;
+        self.arp_mod = if params.arp_mod >= 0.0 {
+            1.0 - (params.arp_mod as f64).powi(2) * 0.9
+        } else {
+            1.0 + (params.arp_mod as f64).powi(2) * 10.0
+        };
+        self.arp_time = 0;
+        self.arp_limit = if params.arp_speed == 1.0 {
+            0
+        } else {
+            ((1.0 - params.arp_speed).powi(2) * 20_000.0 + 32.0) as usize
+        };
+    }
+
+    fn refill_noise(&mut self) {
+        for sample in &mut self.noise_buffer {
+            *sample = self.rng.range_f32(-1.0..1.0);
+        }
+    }
+}
+
+impl<'a> Iterator for Synth<'a> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if !self.playing {
+            return None;
+        }
+        let params = self.params;
+
+        self.repeat_time += 1;
+        if self.repeat_limit != 0 && self.repeat_time >= self.repeat_limit {
+            self.repeat_time = 0;
+            self.reset_frequency();
+        }
+
+        // Frequency slides and arpeggio
+        self.arp_time += 1;
+        if self.arp_limit != 0 && self.arp_time >= self.arp_limit {
+            self.arp_limit = 0;
+            self.period *= self.arp_mod;
+        }
+        self.slide += self.delta_slide;
+        self.period *= self.slide;
+        if self.period > self.max_period {
+            self.period = self.max_period;
+            if params.freq_limit > 0.0 {
+                self.playing = false;
+            }
+        }
+        let mut period = self.period;
+        if self.vib_amp > 0.0 {
+            self.vib_phase += self.vib_speed;
+            period *= 1.0 + (self.vib_phase.sin() * self.vib_amp) as f64;
+        }
+        let period = (period as usize).max(8);
+        self.square_duty = (self.square_duty + self.square_slide).clamp(0.0, 0.5);
+
+        // Volume envelope
+        self.env_time += 1;
+        if self.env_time > self.env_length[self.env_stage] {
+            self.env_time = 0;
+            self.env_stage += 1;
+            if self.env_stage == 3 {
+                self.playing = false;
+                return None;
+            }
+        }
+        let progress = self.env_time as f32 / self.env_length[self.env_stage].max(1) as f32;
+        self.env_volume = match self.env_stage {
+            0 => progress,
+            1 => 1.0 + (1.0 - progress) * 2.0 * params.punch,
+            _ => 1.0 - progress,
+        };
+
+        // Phaser and high-pass filter sweeps
+        self.phaser_phase += self.phaser_delta;
+        let phaser_offset = (self.phaser_phase as i32).unsigned_abs().min(1023) as usize;
+        self.hpf_cutoff = (self.hpf_cutoff * self.hpf_cutoff_delta).clamp(0.000_01, 0.1);
+
+        // 8x supersampling
+        let mut sum = 0.0;
+        for _ in 0..8 {
+            self.phase += 1;
+            if self.phase >= period {
+                self.phase %= period;
+                if params.wave_type == 3 {
+                    self.refill_noise();
+                }
+            }
+
+            let position = self.phase as f32 / period as f32;
+            let mut sample = match params.wave_type {
+                0 => {
+                    if position < self.square_duty {
+                        0.5
+                    } else {
+                        -0.5
+                    }
+                }
+                1 => 1.0 - position * 2.0,
+                2 => (position * TAU).sin(),
+                _ => self.noise_buffer[self.phase * 32 / period],
+            };
+
+            // Low-pass filter
+            let previous = self.lpf_pos;
+            self.lpf_cutoff = (self.lpf_cutoff * self.lpf_cutoff_delta).clamp(0.0, 0.1);
+            if params.lpf_freq != 1.0 {
+                self.lpf_delta += (sample - self.lpf_pos) * self.lpf_cutoff;
+                self.lpf_delta -= self.lpf_delta * self.lpf_damping;
+            } else {
+                self.lpf_pos = sample;
+                self.lpf_delta = 0.0;
+            }
+            self.lpf_pos += self.lpf_delta;
+
+            // High-pass filter
+            self.hpf_pos += self.lpf_pos - previous;
+            self.hpf_pos -= self.hpf_pos * self.hpf_cutoff;
+            sample = self.hpf_pos;
+
+            // Phaser
+            self.phaser_buffer[self.phaser_pos & 1023] = sample;
+            sample += self.phaser_buffer[(self.phaser_pos + 1024 - phaser_offset) & 1023];
+            self.phaser_pos = (self.phaser_pos + 1) & 1023;
+
+            sum += sample * self.env_volume;
+        }
+
+        Some((sum / 8.0 * self.gain).clamp(-1.0, 1.0))
+    }
+}
@@ -0,0 +1,147 @@
+use crate::engine::Bitmap;
+use pix::rgb::Rgba8p;
+
+/// Width of a glyph in the built-in font, in pixels.
+pub const GLYPH_WIDTH: u32 = 5;
+
+/// Height of a glyph in the built-in font, in pixels.
+pub const GLYPH_HEIGHT: u32 = 7;
+
+/// Horizontal distance between glyphs.
+const ADVANCE: u32 = GLYPH_WIDTH + 1;
+
+/// Vertical distance between lines of text.
+const LINE_HEIGHT: u32 = GLYPH_HEIGHT + 2;
+
+/// The built-in 5x7 pixel font, covering ASCII `' '` through `'_'`. Each glyph is stored as seven
+/// rows, with the leftmost pixel in bit 4.
+const GLYPHS: [[u8; GLYPH_HEIGHT as usize]; 64] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04], // '!'
+    [0x0a, 0x0a, 0x00, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a], // '#'
+    [0x04, 0x0f, 0x14, 0x0e, 0x05, 0x1e, 0x04], // '$'
+    [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03], // '%'
+    [0x0c, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0d], // '&'
+    [0x04, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00], // "'"
+    [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02], // '('
+    [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08], // ')'
+    [0x00, 0x04, 0x15, 0x0e, 0x15, 0x04, 0x00], // '*'
+    [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x06, 0x04, 0x08], // ','
+    [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c], // '.'
+    [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00], // '/'
+    [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e], // '0'
+    [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e], // '1'
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f], // '2'
+    [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e], // '3'
+    [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02], // '4'
+    [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e], // '5'
+    [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e], // '6'
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08], // '7'
+    [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e], // '8'
+    [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c], // '9'
+    [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00], // ':'
+    [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x04, 0x08], // ';'
+    [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02], // '<'
+    [0x00, 0x00, 0x1f, 0x00, 0x1f, 0x00, 0x00], // '='
+    [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08], // '>'
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04], // '?'
+    [0x0e, 0x11, 0x01, 0x0d, 0x15, 0x15, 0x0e], // '@'
+    [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11], // 'A'
+    [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e], // 'B'
+    [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e], // 'C'
+    [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c], // 'D'
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f], // 'E'
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10], // 'F'
+    [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f], // 'G'
+    [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11], // 'H'
+    [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e], // 'I'
+    [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c], // 'J'
+    [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11], // 'K'
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f], // 'L'
+    [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11], // 'M'
+    [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11], // 'N'
+    [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e], // 'O'
+    [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10], // 'P'
+    [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d], // 'Q'
+    [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11], // 'R'
+    [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e], // 'S'
+    [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // 'T'
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e], // 'U'
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04], // 'V'
+    [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a], // 'W'
+    [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11], // 'X'
+    [0x11, 0x11, 0x0a, 0x04, 0x04, 0x04, 0x04], // 'Y'
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f], // 'Z'
+    [0x0e, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0e], // '['
+    [0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00], // '\\'
+    [0x0e, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0e], // ']'
+    [0x04, 0x0a, 0x11, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f], // '_'
+];
+
+impl Bitmap {
+    /// Render text with the built-in pixel font and a one pixel drop shadow.
+    ///
+    /// Lines are separated by `'\n'`. Lowercase letters are drawn as uppercase, and unsupported
+    /// characters are drawn as `'?'`.
+    pub fn from_text(text: &str, color: Rgba8p) -> Self {
+        let lines: Vec<Vec<&[u8; 7]>> = text
+            .lines()
+            .map(|line| line.chars().map(glyph).collect())
+            .collect();
+        let (width, height) = text_size(text);
+
+        let shadow = Rgba8p::new(0.0, 0.0, 0.0, 1.0);
+        let clear = Rgba8p::new(0.0, 0.0, 0.0, 0.0);
+        let lit = |x: u32, y: u32| -> bool {
+            let Some(line) = lines.get((y / LINE_HEIGHT) as usize) else {
+                return false;
+            };
+            let Some(glyph) = line.get((x / ADVANCE) as usize) else {
+                return false;
+            };
+            let (x, y) = (x % ADVANCE, y % LINE_HEIGHT);
+
+            x < GLYPH_WIDTH && y < GLYPH_HEIGHT && glyph[y as usize] & (0x10 >> x) != 0
+        };
+
+        let mut bitmap = Self::with_clear(width, height);
+        bitmap.fill_with(|x, y| {
+            if lit(x, y) {
+                color
+            } else if x > 0 && y > 0 && lit(x - 1, y - 1) {
+                shadow
+            } else {
+                clear
+            }
+        });
+
+        bitmap
+    }
+}
+
+/// Get the size of the bitmap that [`Bitmap::from_text`] would create for the given text.
+pub fn text_size(text: &str) -> (u32, u32) {
+    let columns = text.lines().map(|line| line.chars().count()).max();
+    let rows = text.lines().count() as u32;
+
+    match columns {
+        Some(columns) if columns > 0 => (
+            columns as u32 * ADVANCE,
+            rows * LINE_HEIGHT - (LINE_HEIGHT - GLYPH_HEIGHT) + 1,
+        ),
+        _ => (1, 1),
+    }
+}
+
+fn glyph(ch: char) -> &'static [u8; 7] {
+    let index = match ch.to_ascii_uppercase() {
+        ch @ ' '..='_' => ch as usize - ' ' as usize,
+        _ => '?' as usize - ' ' as usize,
+    };
+
+    &GLYPHS[index]
+}
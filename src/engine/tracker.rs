@@ -0,0 +1,1163 @@
+//! FastTracker II extended module (XM) playback.
+//!
+//! Modules are rendered to PCM by the asset loader, so they play through any [`AudioChannel`] like
+//! other audio sources. The song is rendered once through and loops from its restart position, so
+//! a rendered module takes about 350 KB per second of music until the loop point. Songs longer
+//! than five minutes are cut off and loop from the start, which bounds the memory and the time
+//! spent rendering. The loader also produces a [`ModuleTimeline`] asset with the playback position
+//! of every pattern row, which [`Music`] uses to send [`MusicRow`] events in sync with the music.
+//!
+//! The renderer supports sample loops, instrument envelopes, fadeout, auto-vibrato, and the
+//! effects that are commonly used in chiptunes:
+//!
+//! | Effect | Description                 | Effect | Description           |
+//! |--------|-----------------------------|--------|-----------------------|
+//! | `0xy`  | Arpeggio                    | `9xx`  | Sample offset         |
+//! | `1xx`  | Portamento up               | `Axy`  | Volume slide          |
+//! | `2xx`  | Portamento down             | `Bxx`  | Position jump         |
+//! | `3xx`  | Tone portamento             | `Cxx`  | Set volume            |
+//! | `4xy`  | Vibrato                     | `Dxx`  | Pattern break         |
+//! | `5xy`  | Tone portamento + vol slide | `E1x`, `E2x` | Fine portamento |
+//! | `6xy`  | Vibrato + volume slide      | `EAx`, `EBx` | Fine volume slide |
+//! | `8xx`  | Set panning                 | `ECx`  | Note cut              |
+//! | `Fxx`  | Set speed or tempo          | `Gxx`  | Set global volume     |
+//! | `Kxx`  | Key off                     |        |                       |
+//!
+//! [`AudioChannel`]: bevy_kira_audio::AudioChannel
+//! [`Music`]: crate::engine::Music
+
+use ahash::HashSet;
+use bevy::{
+    asset::{AssetLoader, Error, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use bevy_kira_audio::AudioSource;
+use kira::{
+    dsp::Frame,
+    sound::static_sound::{StaticSoundData, StaticSoundSettings},
+    LoopBehavior,
+};
+use std::f32::consts::TAU;
+
+#[derive(Debug)]
+pub(crate) struct TrackerPlugin;
+
+/// Sent by [`Music`](crate::engine::Music) each time a module track reaches a new pattern row.
+///
+/// Use it to sync gameplay to the music, e.g. spawning an enemy wave on every fourth row.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MusicRow {
+    /// Position in the module's pattern order table.
+    pub order: usize,
+    /// Pattern that is playing.
+    pub pattern: usize,
+    /// Row within the pattern.
+    pub row: usize,
+}
+
+/// Playback position of every row in a module, in the order they are played.
+///
+/// Loaded with the `timeline` label, e.g. `music/getting-started.xm#timeline`.
+#[derive(Debug, TypeUuid)]
+#[uuid = "7d7ad1f6-0f6a-4a44-9f5c-3b7c1f27c7d2"]
+pub struct ModuleTimeline {
+    rows: Vec<(f64, MusicRow)>,
+    loop_start: f64,
+}
+
+#[derive(Debug, Default)]
+struct XmLoader;
+
+/// Output sample rate of rendered modules.
+const SAMPLE_RATE: u32 = 44_100;
+
+/// Mixer gain applied to every channel, to leave headroom when several channels play at once.
+const AMPLIFICATION: f32 = 0.5;
+
+/// Note number for key off in pattern data.
+const KEY_OFF: u8 = 97;
+
+/// Most channels a module can have.
+const MAX_CHANNELS: usize = 32;
+
+/// Most rows a pattern can have.
+const MAX_ROWS: usize = 256;
+
+/// Longest song that is rendered, in seconds.
+const MAX_DURATION: f64 = 300.0;
+
+impl Plugin for TrackerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<ModuleTimeline>()
+            .add_asset_loader(XmLoader)
+            .add_event::<MusicRow>();
+    }
+}
+
+impl AssetLoader for XmLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let module = Module::parse(bytes).map_err(Error::msg)?;
+            let (frames, timeline) = Player::new(&module).render(MAX_DURATION);
+
+            let sound = StaticSoundData {
+                sample_rate: SAMPLE_RATE,
+                frames: frames.into(),
+                settings: StaticSoundSettings::new().loop_behavior(LoopBehavior {
+                    start_position: timeline.loop_start,
+                }),
+            };
+            load_context.set_labeled_asset("timeline", LoadedAsset::new(timeline));
+            load_context.set_default_asset(LoadedAsset::new(AudioSource { sound }));
+
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["xm"]
+    }
+}
+
+impl ModuleTimeline {
+    /// Rows that start at or after `from` and before `to`, in seconds.
+    pub fn rows_between(&self, from: f64, to: f64) -> impl Iterator<Item = &MusicRow> {
+        let start = self.rows.partition_point(|(time, _)| *time < from);
+        let end = self.rows.partition_point(|(time, _)| *time < to);
+
+        self.rows[start..end.max(start)].iter().map(|(_, row)| row)
+    }
+
+    /// Position in seconds where the module loops back to its restart position. Module tracks
+    /// loop from here unless their [`Track::loop_from`](crate::engine::Track::loop_from) is set.
+    pub fn loop_start(&self) -> f64 {
+        self.loop_start
+    }
+}
+
+/// A parsed XM file.
+#[derive(Debug)]
+struct Module {
+    channels: usize,
+    restart: usize,
+    linear: bool,
+    speed: u32,
+    bpm: u32,
+    order: Vec<usize>,
+    patterns: Vec<Pattern>,
+    instruments: Vec<Instrument>,
+}
+
+#[derive(Debug)]
+struct Pattern {
+    rows: usize,
+    cells: Vec<Cell>,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+struct Cell {
+    note: u8,
+    instrument: u8,
+    volume: u8,
+    effect: u8,
+    param: u8,
+}
+
+#[derive(Debug, Default)]
+struct Instrument {
+    keymap: Vec<u8>,
+    volume_envelope: Envelope,
+    panning_envelope: Envelope,
+    vibrato_type: u8,
+    vibrato_sweep: u8,
+    vibrato_depth: u8,
+    vibrato_rate: u8,
+    fadeout: u16,
+    samples: Vec<Sample>,
+}
+
+#[derive(Debug, Default)]
+struct Envelope {
+    points: Vec<(u16, u16)>,
+    sustain: Option<usize>,
+    looped: Option<(usize, usize)>,
+}
+
+#[derive(Debug)]
+struct Sample {
+    data: Vec<f32>,
+    loop_start: usize,
+    loop_length: usize,
+    ping_pong: bool,
+    volume: u8,
+    finetune: i8,
+    panning: u8,
+    relative_note: i8,
+}
+
+/// Reads little-endian values from the module data.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn slice(&self, offset: usize, len: usize) -> Result<&'a [u8], String> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(offset..end))
+            .ok_or_else(|| format!("Unexpected end of file at offset {offset}"))
+    }
+
+    fn u8(&self, offset: usize) -> Result<u8, String> {
+        Ok(self.slice(offset, 1)?[0])
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, String> {
+        let bytes = self.slice(offset, 2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, String> {
+        let bytes = self.slice(offset, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+impl Module {
+    fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let reader = Reader { bytes };
+        if reader.slice(0, 17)? != b"Extended Module: " {
+            return Err("Not an XM file".to_string());
+        }
+        let version = reader.u16(58)?;
+        if version < 0x0104 {
+            return Err(format!("Unsupported XM version {version:#06x}"));
+        }
+
+        let header_size = reader.u32(60)? as usize;
+        let song_length = reader.u16(64)? as usize;
+        let restart = reader.u16(66)? as usize;
+        let channels = reader.u16(68)? as usize;
+        if channels == 0 || channels > MAX_CHANNELS {
+            return Err(format!("Unsupported channel count {channels}"));
+        }
+        let pattern_count = reader.u16(70)? as usize;
+        let instrument_count = reader.u16(72)? as usize;
+        let linear = reader.u16(74)? & 1 == 1;
+        let speed = reader.u16(76)? as u32;
+        let bpm = reader.u16(78)? as u32;
+        let order = reader
+            .slice(80, song_length.min(256))?
+            .iter()
+            .map(|&pattern| pattern as usize)
+            .collect();
+
+        let mut offset = 60 + header_size;
+        let mut patterns = Vec::with_capacity(pattern_count);
+        for _ in 0..pattern_count {
+            let (pattern, size) = Pattern::parse(&reader, offset, channels)?;
+            patterns.push(pattern);
+            offset += size;
+        }
+
+        let mut instruments = Vec::with_capacity(instrument_count);
+        for _ in 0..instrument_count {
+            let (instrument, size) = Instrument::parse(&reader, offset)?;
+            instruments.push(instrument);
+            offset += size;
+        }
+
+        Ok(Self {
+            channels,
+            restart: if restart < song_length { restart } else { 0 },
+            linear,
+            speed: speed.max(1),
+            bpm: bpm.max(32),
+            order,
+            patterns,
+            instruments,
+        })
+    }
+
+    fn cell(&self, pattern: usize, row: usize, channel: usize) -> Cell {
+        self.patterns
+            .get(pattern)
+            .and_then(|pattern| pattern.cells.get(row * self.channels + channel))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn rows(&self, pattern: usize) -> usize {
+        // Missing patterns are empty with 64 rows.
+        self.patterns
+            .get(pattern)
+            .map_or(64, |pattern| pattern.rows)
+    }
+}
+
+impl Pattern {
+    /// Returns the pattern and its size in bytes.
+    fn parse(reader: &Reader, offset: usize, channels: usize) -> Result<(Self, usize), String> {
+        let header_size = reader.u32(offset)? as usize;
+        let rows = reader.u16(offset + 5)? as usize;
+        if rows > MAX_ROWS {
+            return Err(format!("Unsupported pattern length {rows}"));
+        }
+        let packed_size = reader.u16(offset + 7)? as usize;
+        let data = reader.slice(offset + header_size, packed_size)?;
+
+        let mut cells = vec![Cell::default(); rows * channels];
+        if packed_size > 0 {
+            let mut i = 0;
+            let mut next = || {
+                let byte = data.get(i).copied().unwrap_or_default();
+                i += 1;
+                byte
+            };
+            for cell in &mut cells {
+                let flags = next();
+                if flags & 0x80 == 0 {
+                    *cell = Cell {
+                        note: flags,
+                        instrument: next(),
+                        volume: next(),
+                        effect: next(),
+                        param: next(),
+                    };
+                } else {
+                    let mut field = |bit: u8| if flags & bit != 0 { next() } else { 0 };
+                    *cell = Cell {
+                        note: field(0x01),
+                        instrument: field(0x02),
+                        volume: field(0x04),
+                        effect: field(0x08),
+                        param: field(0x10),
+                    };
+                }
+            }
+        }
+
+        Ok((Self { rows, cells }, header_size + packed_size))
+    }
+}
+
+impl Instrument {
+    /// Returns the instrument and its size in bytes, including sample data.
+    fn parse(reader: &Reader, offset: usize) -> Result<(Self, usize), String> {
+        let size = reader.u32(offset)? as usize;
+        let sample_count = reader.u16(offset + 27)? as usize;
+        if sample_count == 0 {
+            return Ok((Self::default(), size));
+        }
+
+        let sample_header_size = reader.u32(offset + 29)? as usize;
+        let mut instrument = Self {
+            keymap: reader.slice(offset + 33, 96)?.to_vec(),
+            volume_envelope: Envelope::parse(reader, offset + 129, offset + 225, offset + 227)?,
+            panning_envelope: Envelope::parse(reader, offset + 177, offset + 226, offset + 230)?,
+            vibrato_type: reader.u8(offset + 235)?,
+            vibrato_sweep: reader.u8(offset + 236)?,
+            vibrato_depth: reader.u8(offset + 237)?,
+            vibrato_rate: reader.u8(offset + 238)?,
+            fadeout: reader.u16(offset + 239)?,
+            samples: Vec::with_capacity(sample_count),
+        };
+
+        // Sample headers are followed by the data for all samples.
+        let mut headers = offset + size;
+        let mut data = headers + sample_header_size * sample_count;
+        for _ in 0..sample_count {
+            let length = reader.u32(headers)? as usize;
+            let kind = reader.u8(headers + 14)?;
+            let sixteen_bit = kind & 0x10 != 0;
+            let bytes = reader.slice(data, length)?;
+
+            // Samples are delta encoded.
+            let decoded: Vec<f32> = if sixteen_bit {
+                let mut value = 0_i16;
+                bytes
+                    .chunks_exact(2)
+                    .map(|delta| {
+                        value = value.wrapping_add(i16::from_le_bytes([delta[0], delta[1]]));
+                        value as f32 / 32768.0
+                    })
+                    .collect()
+            } else {
+                let mut value = 0_i8;
+                bytes
+                    .iter()
+                    .map(|&delta| {
+                        value = value.wrapping_add(delta as i8);
+                        value as f32 / 128.0
+                    })
+                    .collect()
+            };
+
+            let width = if sixteen_bit { 2 } else { 1 };
+            let mut loop_start = reader.u32(headers + 4)? as usize / width;
+            let mut loop_length = reader.u32(headers + 8)? as usize / width;
+            if kind & 3 == 0 || loop_start >= decoded.len() {
+                loop_start = 0;
+                loop_length = 0;
+            }
+            loop_length = loop_length.min(decoded.len() - loop_start);
+
+            instrument.samples.push(Sample {
+                data: decoded,
+                loop_start,
+                loop_length,
+                ping_pong: kind & 3 == 2,
+                volume: reader.u8(headers + 12)?.min(64),
+                finetune: reader.u8(headers + 13)? as i8,
+                panning: reader.u8(headers + 15)?,
+                relative_note: reader.u8(headers + 16)? as i8,
+            });
+
+            headers += sample_header_size;
+            data += length;
+        }
+
+        Ok((instrument, data - offset))
+    }
+}
+
+impl Envelope {
+    /// Parse an envelope from its points, its point count, and its sustain point. The loop points
+    /// follow the sustain point, and the flags are 6 bytes after the point count.
+    fn parse(reader: &Reader, points: usize, count: usize, sustain: usize) -> Result<Self, String> {
+        let flags = reader.u8(count + 8)?;
+        let count = reader.u8(count)?.min(12) as usize;
+        if flags & 1 == 0 || count == 0 {
+            return Ok(Self::default());
+        }
+
+        let points = (0..count)
+            .map(|i| Ok((reader.u16(points + i * 4)?, reader.u16(points + i * 4 + 2)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+        let last = points.len() - 1;
+        let sustain_point = reader.u8(sustain)? as usize;
+        let loop_start = reader.u8(sustain + 1)? as usize;
+        let loop_end = reader.u8(sustain + 2)? as usize;
+
+        Ok(Self {
+            sustain: (flags & 2 != 0).then_some(sustain_point.min(last)),
+            looped: (flags & 4 != 0).then_some((loop_start.min(last), loop_end.min(last))),
+            points,
+        })
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.points.is_empty()
+    }
+
+    /// Envelope value in the range `[0, 64]` at the given tick.
+    fn value(&self, tick: u16) -> f32 {
+        let index = self.points.partition_point(|(x, _)| *x <= tick);
+        match (index.checked_sub(1), self.points.get(index)) {
+            (Some(i), Some(&(x1, y1))) => {
+                let (x0, y0) = self.points[i];
+                let t = tick.saturating_sub(x0) as f32 / x1.saturating_sub(x0).max(1) as f32;
+
+                y0 as f32 + (y1 as f32 - y0 as f32) * t
+            }
+            (Some(i), None) => self.points[i].1 as f32,
+            (None, _) => self.points[0].1 as f32,
+        }
+        .min(64.0)
+    }
+
+    /// Advance the envelope position by one tick.
+    fn advance(&self, tick: u16, key_on: bool) -> u16 {
+        if let Some(sustain) = self.sustain {
+            if key_on && tick >= self.points[sustain].0 {
+                return self.points[sustain].0;
+            }
+        }
+        if let Some((start, end)) = self.looped {
+            if tick.saturating_add(1) >= self.points[end].0 {
+                return self.points[start].0;
+            }
+        }
+
+        tick.saturating_add(1)
+    }
+}
+
+/// Renders a [`Module`] to PCM, one tick at a time.
+struct Player<'a> {
+    module: &'a Module,
+    channels: Vec<Channel>,
+    speed: u32,
+    bpm: u32,
+    global_volume: f32,
+    order: usize,
+    row: usize,
+    tick: u32,
+    jump: Option<(usize, usize)>,
+}
+
+/// Playback state of a single module channel.
+#[derive(Debug, Default)]
+struct Channel {
+    instrument: Option<usize>,
+    sample: Option<usize>,
+    position: f64,
+    backwards: bool,
+    period: f32,
+    target_period: f32,
+    volume: f32,
+    panning: f32,
+    key_on: bool,
+    fadeout: f32,
+    volume_tick: u16,
+    panning_tick: u16,
+    vibrato_tick: u32,
+    cell: Cell,
+    arpeggio: f32,
+    vibrato: f32,
+    vibrato_position: u8,
+    vibrato_speed: u8,
+    vibrato_depth: u8,
+    portamento_up: u8,
+    portamento_down: u8,
+    tone_portamento: u8,
+    volume_slide: u8,
+    fine_portamento_up: u8,
+    fine_portamento_down: u8,
+    fine_volume_slide_up: u8,
+    fine_volume_slide_down: u8,
+    sample_offset: u8,
+}
+
+impl<'a> Player<'a> {
+    fn new(module: &'a Module) -> Self {
+        Self {
+            module,
+            channels: (0..module.channels).map(|_| Channel::default()).collect(),
+            speed: module.speed,
+            bpm: module.bpm,
+            global_volume: 1.0,
+            order: 0,
+            row: 0,
+            tick: 0,
+            jump: None,
+        }
+    }
+
+    /// Render the module once through, stopping when it ends or loops back to a row that was
+    /// already played. Songs that run past `max_duration` seconds stop at the next row and loop
+    /// from the start.
+    fn render(mut self, max_duration: f64) -> (Vec<Frame>, ModuleTimeline) {
+        let max_frames = (max_duration * SAMPLE_RATE as f64) as usize;
+        let mut frames = Vec::new();
+        let mut rows: Vec<(f64, MusicRow)> = Vec::new();
+        let mut visited = HashSet::default();
+        let mut remainder = 0.0;
+
+        let loop_start = loop {
+            if self.order >= self.module.order.len() {
+                // The song ended, continue from its restart position.
+                let restart = self.module.restart;
+                break rows
+                    .iter()
+                    .find(|(_, row)| row.order == restart)
+                    .map_or(0.0, |(time, _)| *time);
+            }
+            let pattern = self.module.order[self.order];
+            if self.tick == 0 {
+                if frames.len() >= max_frames {
+                    warn!("Module is longer than {max_duration} seconds, cutting it off");
+                    break 0.0;
+                }
+                if !visited.insert((self.order, self.row)) {
+                    let (order, row) = (self.order, self.row);
+                    break rows
+                        .iter()
+                        .find(|(_, r)| r.order == order && r.row == row)
+                        .map_or(0.0, |(time, _)| *time);
+                }
+                let time = frames.len() as f64 / SAMPLE_RATE as f64;
+                let row = MusicRow {
+                    order: self.order,
+                    pattern,
+                    row: self.row,
+                };
+                rows.push((time, row));
+                self.start_row(pattern);
+            } else {
+                self.update_tick();
+            }
+            self.update_channels();
+
+            // Ticks are 2.5 / BPM seconds long.
+            let length = SAMPLE_RATE as f64 * 2.5 / self.bpm as f64 + remainder;
+            remainder = length.fract();
+            self.mix(&mut frames, length as usize);
+
+            self.tick += 1;
+            if self.tick >= self.speed {
+                self.tick = 0;
+                self.next_row(pattern);
+            }
+        };
+
+        (frames, ModuleTimeline { rows, loop_start })
+    }
+
+    fn next_row(&mut self, pattern: usize) {
+        if let Some((order, row)) = self.jump.take() {
+            self.order = order;
+            self.row = row;
+        } else {
+            self.row += 1;
+            if self.row >= self.module.rows(pattern) {
+                self.order += 1;
+                self.row = 0;
+            }
+        }
+        if self
+            .module
+            .order
+            .get(self.order)
+            .is_some_and(|&pattern| self.row >= self.module.rows(pattern))
+        {
+            self.row = 0;
+        }
+    }
+
+    /// Process the cells of a new row on tick 0.
+    fn start_row(&mut self, pattern: usize) {
+        let module = self.module;
+        for (index, channel) in self.channels.iter_mut().enumerate() {
+            let cell = module.cell(pattern, self.row, index);
+            channel.cell = cell;
+            channel.arpeggio = 0.0;
+            channel.vibrato = 0.0;
+
+            let tone_portamento = matches!(cell.effect, 0x3 | 0x5) || cell.volume & 0xf0 == 0xf0;
+
+            if cell.instrument > 0 {
+                let instrument = cell.instrument as usize - 1;
+                channel.instrument = Some(instrument).filter(|&i| i < module.instruments.len());
+            }
+
+            if cell.note == KEY_OFF || (cell.effect == 0x14 && cell.param == 0) {
+                channel.key_off(module);
+            } else if (1..KEY_OFF).contains(&cell.note) {
+                channel.trigger(module, cell.note, tone_portamento);
+            }
+
+            // An instrument number resets the volume, panning, and envelopes.
+            if cell.instrument > 0 {
+                if let Some(sample) = channel.sample(module) {
+                    channel.volume = sample.volume as f32;
+                    channel.panning = sample.panning as f32;
+                }
+                channel.key_on = true;
+                channel.fadeout = 1.0;
+                channel.volume_tick = 0;
+                channel.panning_tick = 0;
+                channel.vibrato_tick = 0;
+            }
+
+            // Volume column
+            match cell.volume {
+                0x10..=0x50 => channel.volume = (cell.volume - 0x10) as f32,
+                0x80..=0x8f => channel.slide_volume(-((cell.volume & 0xf) as f32)),
+                0x90..=0x9f => channel.slide_volume((cell.volume & 0xf) as f32),
+                0xc0..=0xcf => channel.panning = ((cell.volume & 0xf) * 17) as f32,
+                0xf0..=0xff if cell.volume & 0xf > 0 => {
+                    channel.tone_portamento = (cell.volume & 0xf) << 4;
+                }
+                _ => (),
+            }
+
+            let param = cell.param;
+            match cell.effect {
+                0x1 if param > 0 => channel.portamento_up = param,
+                0x2 if param > 0 => channel.portamento_down = param,
+                0x3 if param > 0 => channel.tone_portamento = param,
+                0x4 => {
+                    if param >> 4 > 0 {
+                        channel.vibrato_speed = param >> 4;
+                    }
+                    if param & 0xf > 0 {
+                        channel.vibrato_depth = param & 0xf;
+                    }
+                }
+                0x5 | 0x6 | 0xa if param > 0 => channel.volume_slide = param,
+                0x8 => channel.panning = param as f32,
+                0xb => self.jump = Some((param as usize, 0)),
+                0xc => channel.volume = param.min(64) as f32,
+                0xd => {
+                    let row = (param >> 4) as usize * 10 + (param & 0xf) as usize;
+                    let order = self.jump.map_or(self.order + 1, |(order, _)| order);
+                    self.jump = Some((order, row));
+                }
+                0xe => {
+                    let value = param & 0xf;
+                    match param >> 4 {
+                        0x1 => {
+                            if value > 0 {
+                                channel.fine_portamento_up = value;
+                            }
+                            channel.period -= channel.fine_portamento_up as f32 * 4.0;
+                        }
+                        0x2 => {
+                            if value > 0 {
+                                channel.fine_portamento_down = value;
+                            }
+                            channel.period += channel.fine_portamento_down as f32 * 4.0;
+                        }
+                        0xa => {
+                            if value > 0 {
+                                channel.fine_volume_slide_up = value;
+                            }
+                            channel.slide_volume(channel.fine_volume_slide_up as f32);
+                        }
+                        0xb => {
+                            if value > 0 {
+                                channel.fine_volume_slide_down = value;
+                            }
+                            channel.slide_volume(-(channel.fine_volume_slide_down as f32));
+                        }
+                        0xc if value == 0 => channel.volume = 0.0,
+                        _ => (),
+                    }
+                }
+                0xf if param > 0 => {
+                    if param < 32 {
+                        self.speed = param as u32;
+                    } else {
+                        self.bpm = param as u32;
+                    }
+                }
+                0x10 => self.global_volume = param.min(64) as f32 / 64.0,
+                _ => (),
+            }
+        }
+    }
+
+    /// Process continuous effects on ticks after the first.
+    fn update_tick(&mut self) {
+        let tick = self.tick;
+        for channel in &mut self.channels {
+            let cell = channel.cell;
+            channel.arpeggio = 0.0;
+            channel.vibrato = 0.0;
+
+            match cell.volume >> 4 {
+                0x6 => channel.slide_volume(-((cell.volume & 0xf) as f32)),
+                0x7 => channel.slide_volume((cell.volume & 0xf) as f32),
+                0xf => channel.slide_to_target(),
+                _ => (),
+            }
+
+            match cell.effect {
+                0x0 if cell.param > 0 => {
+                    channel.arpeggio = match tick % 3 {
+                        1 => (cell.param >> 4) as f32,
+                        2 => (cell.param & 0xf) as f32,
+                        _ => 0.0,
+                    };
+                }
+                0x1 => channel.period -= channel.portamento_up as f32 * 4.0,
+                0x2 => channel.period += channel.portamento_down as f32 * 4.0,
+                0x3 => channel.slide_to_target(),
+                0x4 => channel.apply_vibrato(),
+                0x5 => {
+                    channel.slide_to_target();
+                    channel.apply_volume_slide();
+                }
+                0x6 => {
+                    channel.apply_vibrato();
+                    channel.apply_volume_slide();
+                }
+                0xa => channel.apply_volume_slide(),
+                0xe if cell.param >> 4 == 0xc && tick == (cell.param & 0xf) as u32 => {
+                    channel.volume = 0.0;
+                }
+                0x14 if tick == cell.param as u32 => channel.key_off(self.module),
+                _ => (),
+            }
+
+            // Keep the vibrato waveform running while the effect is held.
+            if matches!(cell.effect, 0x4 | 0x6) {
+                channel.vibrato_position =
+                    channel.vibrato_position.wrapping_add(channel.vibrato_speed);
+            }
+        }
+    }
+
+    /// Advance envelopes, fadeout, and auto-vibrato once per tick.
+    fn update_channels(&mut self) {
+        let module = self.module;
+        for channel in &mut self.channels {
+            let Some(instrument) = channel.instrument.map(|i| &module.instruments[i]) else {
+                continue;
+            };
+
+            if !channel.key_on {
+                channel.fadeout = (channel.fadeout - instrument.fadeout as f32 / 65536.0).max(0.0);
+            }
+            channel.volume_tick = instrument
+                .volume_envelope
+                .advance(channel.volume_tick, channel.key_on);
+            channel.panning_tick = instrument
+                .panning_envelope
+                .advance(channel.panning_tick, channel.key_on);
+            channel.vibrato_tick += 1;
+        }
+    }
+
+    /// Mix `length` frames of all channels into the output.
+    fn mix(&mut self, frames: &mut Vec<Frame>, length: usize) {
+        let start = frames.len();
+        frames.resize(start + length, Frame::ZERO);
+        let output = &mut frames[start..];
+
+        let module = self.module;
+        for channel in &mut self.channels {
+            let (Some(instrument), Some(sample)) = (
+                channel.instrument.map(|i| &module.instruments[i]),
+                channel.sample(module),
+            ) else {
+                continue;
+            };
+
+            let volume_envelope = if instrument.volume_envelope.is_enabled() {
+                instrument.volume_envelope.value(channel.volume_tick) / 64.0
+            } else if channel.key_on {
+                1.0
+            } else {
+                0.0
+            };
+            let volume = channel.volume.clamp(0.0, 64.0) / 64.0
+                * volume_envelope
+                * channel.fadeout
+                * self.global_volume
+                * AMPLIFICATION;
+            if volume <= 0.0 || sample.data.is_empty() {
+                continue;
+            }
+
+            let mut panning = channel.panning;
+            if instrument.panning_envelope.is_enabled() {
+                let envelope = instrument.panning_envelope.value(channel.panning_tick) - 32.0;
+                panning += envelope * (128.0 - (panning - 128.0).abs()) / 32.0;
+            }
+            let panning = panning.clamp(0.0, 255.0) / 255.0;
+            let (left, right) = (volume * (1.0 - panning).sqrt(), volume * panning.sqrt());
+
+            let step = channel.frequency(module.linear, instrument) / SAMPLE_RATE as f64;
+            for frame in output.iter_mut() {
+                let Some(value) = channel.next_sample(sample, step) else {
+                    break;
+                };
+                frame.left += value * left;
+                frame.right += value * right;
+            }
+        }
+
+        for frame in output {
+            frame.left = frame.left.clamp(-1.0, 1.0);
+            frame.right = frame.right.clamp(-1.0, 1.0);
+        }
+    }
+}
+
+impl Channel {
+    fn sample<'m>(&self, module: &'m Module) -> Option<&'m Sample> {
+        module
+            .instruments
+            .get(self.instrument?)?
+            .samples
+            .get(self.sample?)
+    }
+
+    fn trigger(&mut self, module: &Module, note: u8, tone_portamento: bool) {
+        let Some(instrument) = self.instrument.map(|i| &module.instruments[i]) else {
+            return;
+        };
+        if !tone_portamento || self.sample.is_none() {
+            let sample = instrument.keymap.get(note as usize - 1).copied();
+            self.sample = sample
+                .map(|sample| sample as usize)
+                .filter(|&sample| sample < instrument.samples.len());
+        }
+        let Some(sample) = self.sample(module) else {
+            return;
+        };
+
+        let note = note as i32 - 1 + sample.relative_note as i32;
+        let period = period(module.linear, note, sample.finetune);
+        if tone_portamento && self.period > 0.0 {
+            self.target_period = period;
+            return;
+        }
+
+        self.period = period;
+        self.target_period = period;
+        self.backwards = false;
+        self.position = 0.0;
+        self.vibrato_position = 0;
+        if self.cell.effect == 0x9 {
+            if self.cell.param > 0 {
+                self.sample_offset = self.cell.param;
+            }
+            self.position = self.sample_offset as f64 * 256.0;
+        }
+    }
+
+    fn key_off(&mut self, module: &Module) {
+        self.key_on = false;
+        let has_envelope = self
+            .instrument
+            .is_some_and(|i| module.instruments[i].volume_envelope.is_enabled());
+        if !has_envelope {
+            self.volume = 0.0;
+        }
+    }
+
+    fn slide_volume(&mut self, amount: f32) {
+        self.volume = (self.volume + amount).clamp(0.0, 64.0);
+    }
+
+    fn apply_volume_slide(&mut self) {
+        let up = (self.volume_slide >> 4) as f32;
+        let down = (self.volume_slide & 0xf) as f32;
+        self.slide_volume(if up > 0.0 { up } else { -down });
+    }
+
+    fn apply_vibrato(&mut self) {
+        let phase = self.vibrato_position as f32 / 64.0 * TAU;
+        self.vibrato = phase.sin() * self.vibrato_depth as f32 * 255.0 / 32.0;
+    }
+
+    fn slide_to_target(&mut self) {
+        let speed = self.tone_portamento as f32 * 4.0;
+        if self.period < self.target_period {
+            self.period = (self.period + speed).min(self.target_period);
+        } else {
+            self.period = (self.period - speed).max(self.target_period);
+        }
+    }
+
+    /// Playback rate in samples per second, including pitch effects.
+    fn frequency(&self, linear: bool, instrument: &Instrument) -> f64 {
+        let mut period = self.period + self.vibrato;
+
+        // Instrument auto-vibrato, ramped in over the sweep duration.
+        if instrument.vibrato_depth > 0 {
+            let phase = (self.vibrato_tick * instrument.vibrato_rate as u32 % 256) as f32 / 256.0;
+            let wave = match instrument.vibrato_type {
+                1 => {
+                    if phase < 0.5 {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                }
+                2 => 1.0 - phase * 2.0,
+                3 => phase * 2.0 - 1.0,
+                _ => (phase * TAU).sin(),
+            };
+            let sweep = match instrument.vibrato_sweep {
+                0 => 1.0,
+                sweep => (self.vibrato_tick as f32 / sweep as f32).min(1.0),
+            };
+            period += wave * instrument.vibrato_depth as f32 * sweep;
+        }
+
+        let frequency = if linear {
+            8363.0 * 2.0_f64.powf((4608.0 - period as f64) / 768.0)
+        } else {
+            8363.0 * 1712.0 / (period.max(1.0) as f64)
+        };
+
+        frequency * 2.0_f64.powf(self.arpeggio as f64 / 12.0)
+    }
+
+    /// Read the next interpolated value from the sample, following its loop. Returns `None` when
+    /// a sample without a loop ends.
+    fn next_sample(&mut self, sample: &Sample, step: f64) -> Option<f32> {
+        let len = sample.data.len();
+        let index = self.position as usize;
+        if index >= len {
+            return None;
+        }
+
+        let next = if index + 1 < len {
+            sample.data[index + 1]
+        } else if sample.loop_length > 0 {
+            sample.data[sample.loop_start]
+        } else {
+            sample.data[index]
+        };
+        let t = self.position.fract() as f32;
+        let value = sample.data[index] + (next - sample.data[index]) * t;
+
+        if self.backwards {
+            self.position -= step;
+        } else {
+            self.position += step;
+        }
+
+        if sample.loop_length > 0 {
+            let start = sample.loop_start as f64;
+            let end = (sample.loop_start + sample.loop_length) as f64;
+            if sample.ping_pong {
+                if self.position >= end {
+                    self.position = (end - (self.position - end)).max(start);
+                    self.backwards = true;
+                } else if self.backwards && self.position < start {
+                    self.position = (start + (start - self.position)).min(end - 1.0);
+                    self.backwards = false;
+                }
+            } else if self.position >= end {
+                self.position = start + (self.position - end) % sample.loop_length as f64;
+            }
+        }
+
+        Some(value)
+    }
+}
+
+/// Period for a zero-based note, in linear or Amiga period units.
+fn period(linear: bool, note: i32, finetune: i8) -> f32 {
+    if linear {
+        7680.0 - note as f32 * 64.0 - finetune as f32 / 2.0
+    } else {
+        let semitones = note as f32 - 48.0 + finetune as f32 / 128.0;
+        1712.0 * 2.0_f32.powf(-semitones / 12.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GETTING_STARTED: &[u8] = include_bytes!("../../assets/music/getting-started.xm");
+
+    /// Header of an empty module with the given channel count.
+    fn header(channels: u16) -> Vec<u8> {
+        let mut bytes = vec![0; 80 + 256];
+        bytes[..17].copy_from_slice(b"Extended Module: ");
+        bytes[58..60].copy_from_slice(&0x0104_u16.to_le_bytes());
+        bytes[60..64].copy_from_slice(&276_u32.to_le_bytes());
+        bytes[68..70].copy_from_slice(&channels.to_le_bytes());
+        bytes[76..78].copy_from_slice(&6_u16.to_le_bytes());
+        bytes[78..80].copy_from_slice(&125_u16.to_le_bytes());
+
+        bytes
+    }
+
+    #[test]
+    fn parse_getting_started() {
+        let module = Module::parse(GETTING_STARTED).unwrap();
+
+        assert!(module.channels > 0);
+        assert!(!module.order.is_empty());
+        assert!(!module.patterns.is_empty());
+        assert!(!module.instruments.is_empty());
+    }
+
+    #[test]
+    fn render_getting_started() {
+        let module = Module::parse(GETTING_STARTED).unwrap();
+        let (frames, timeline) = Player::new(&module).render(MAX_DURATION);
+        let duration = frames.len() as f64 / SAMPLE_RATE as f64;
+
+        // The module is the source of the 115.2 second OGG it replaced.
+        assert!((duration - 115.2).abs() < 0.1, "{duration}");
+        assert!(frames
+            .iter()
+            .all(|frame| frame.left.is_finite() && frame.right.is_finite()));
+        assert!(frames.iter().any(|frame| frame.left != 0.0));
+
+        assert!(timeline.loop_start() < duration);
+        assert_eq!(
+            timeline.rows_between(0.0, f64::INFINITY).count(),
+            timeline.rows.len()
+        );
+        assert!(timeline.rows.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
+
+    #[test]
+    fn render_is_cut_off() {
+        let module = Module::parse(GETTING_STARTED).unwrap();
+        let (frames, timeline) = Player::new(&module).render(10.0);
+        let duration = frames.len() as f64 / SAMPLE_RATE as f64;
+
+        // Rows of this module are well under a second long.
+        assert!((10.0..11.0).contains(&duration), "{duration}");
+        assert_eq!(timeline.loop_start(), 0.0);
+        assert!(timeline.rows.iter().all(|(time, _)| *time < 10.0));
+    }
+
+    #[test]
+    fn truncated_files() {
+        for len in [0, 16, 17, 59, 60, 79, 80, 200] {
+            assert!(Module::parse(&GETTING_STARTED[..len]).is_err(), "{len}");
+        }
+        // Cut through pattern and sample data.
+        for len in [
+            400,
+            1000,
+            GETTING_STARTED.len() / 2,
+            GETTING_STARTED.len() - 1,
+        ] {
+            assert!(Module::parse(&GETTING_STARTED[..len]).is_err(), "{len}");
+        }
+    }
+
+    #[test]
+    fn malformed_headers() {
+        let mut bytes = GETTING_STARTED.to_vec();
+        bytes[0] = b'X';
+        assert!(Module::parse(&bytes).is_err());
+
+        let mut bytes = GETTING_STARTED.to_vec();
+        bytes[58..60].copy_from_slice(&0x0103_u16.to_le_bytes());
+        assert!(Module::parse(&bytes).is_err());
+
+        // Header size past the end of the file.
+        let mut bytes = GETTING_STARTED.to_vec();
+        bytes[60..64].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Module::parse(&bytes).is_err());
+
+        assert!(Module::parse(&header(0)).is_err());
+        assert!(Module::parse(&header(MAX_CHANNELS as u16 + 1)).is_err());
+        assert!(Module::parse(&header(u16::MAX)).is_err());
+    }
+
+    #[test]
+    fn empty_module() {
+        let module = Module::parse(&header(4)).unwrap();
+        let (frames, timeline) = Player::new(&module).render(MAX_DURATION);
+
+        assert!(frames.is_empty());
+        assert_eq!(timeline.loop_start(), 0.0);
+    }
+
+    #[test]
+    fn unsorted_envelope() {
+        let envelope = Envelope {
+            points: vec![(10, 64), (5, 0), (u16::MAX, 32)],
+            sustain: None,
+            looped: Some((0, 2)),
+        };
+
+        for tick in [0, 5, 7, 10, 20, u16::MAX] {
+            let value = envelope.value(tick);
+            assert!((0.0..=64.0).contains(&value), "{tick}: {value}");
+        }
+        assert_eq!(envelope.advance(u16::MAX, false), 10);
+    }
+}
@@ -0,0 +1,249 @@
+use crate::engine::{Bitmap, Camera, RenderLayer, RngSource, ScreenSpace};
+use bevy::{ecs::schedule::StateData, prelude::*};
+use pix::rgb::Rgba8p;
+use serde::Deserialize;
+use std::marker::PhantomData;
+
+/// Drives [`Transition`] requests for the state type `S`.
+#[derive(Debug)]
+pub(crate) struct TransitionPlugin<S> {
+    marker: PhantomData<S>,
+}
+
+/// The `Transition` resource switches between states with a visual effect.
+///
+/// A transition runs the outgoing effect until the screen is fully covered, switches to the new
+/// state, then runs the incoming effect to reveal the new scene.
+#[derive(Resource)]
+pub struct Transition<S> {
+    phase: Option<Phase<S>>,
+}
+
+/// Visual effects for [`Transition`]s. Each effect takes the duration of one half of the
+/// transition in seconds.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+pub enum Effect {
+    /// Switch states immediately.
+    Cut,
+    /// Fade to black and back.
+    Fade(f32),
+    /// A black curtain sweeps horizontally across the screen.
+    Wipe(f32),
+    /// A closing (and opening) circle centered on the screen.
+    Iris(f32),
+    /// Individual pixels turn black in a random order.
+    Dissolve(f32),
+    /// The screen is pixelated into increasingly large blocks while fading to black.
+    Mosaic(f32),
+}
+
+enum Phase<S> {
+    Out {
+        next: S,
+        effect: Effect,
+        timer: Timer,
+    },
+    In {
+        effect: Effect,
+        timer: Timer,
+    },
+}
+
+/// Marker for the full-screen bitmap that renders the current effect.
+#[derive(Component, Debug)]
+struct TransitionOverlay;
+
+/// The largest block size used by [`Effect::Mosaic`].
+const MOSAIC_SIZE: f32 = 16.0;
+
+impl<S> Default for TransitionPlugin<S> {
+    fn default() -> Self {
+        Self {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<S: StateData> Plugin for TransitionPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Transition::<S> { phase: None })
+            .add_system(Self::update);
+    }
+}
+
+impl<S: StateData> TransitionPlugin<S> {
+    fn update(
+        mut commands: Commands,
+        mut transition: ResMut<Transition<S>>,
+        mut state: ResMut<State<S>>,
+        mut camera: ResMut<Camera>,
+        mut overlay: Query<(Entity, &mut Bitmap), With<TransitionOverlay>>,
+        time: Res<Time>,
+    ) {
+        let Some(phase) = transition.phase.as_mut() else {
+            return;
+        };
+
+        // Coverage goes from 0.0 (scene fully visible) to 1.0 (screen fully covered).
+        let (effect, coverage, finished) = match phase {
+            Phase::Out { effect, timer, .. } => {
+                timer.tick(time.delta());
+                (*effect, timer.percent(), timer.finished())
+            }
+            Phase::In { effect, timer } => {
+                timer.tick(time.delta());
+                (*effect, timer.percent_left(), timer.finished())
+            }
+        };
+        let incoming = matches!(phase, Phase::In { .. });
+
+        let size = *camera.size();
+        match overlay.get_single_mut() {
+            Ok((_, mut bitmap)) => effect.render(&mut bitmap, coverage, incoming),
+            Err(_) => {
+                let mut bitmap = Bitmap::with_clear(size.x as u32, size.y as u32);
+                effect.render(&mut bitmap, coverage, incoming);
+
+                let transform = Transform::from_xyz(0.0, 0.0, f32::INFINITY);
+                commands.spawn((
+                    bitmap,
+                    transform,
+                    ScreenSpace,
+                    RenderLayer::Hud,
+                    TransitionOverlay,
+                ));
+            }
+        }
+        camera.set_mosaic(effect.mosaic_size(coverage));
+
+        if !finished {
+            return;
+        }
+
+        match transition.phase.take() {
+            Some(Phase::Out { next, effect, .. }) => {
+                // The state change is dropped if it can't be queued, e.g. the state is already
+                // current. The screen is still revealed.
+                if let Err(err) = state.set(next.clone()) {
+                    warn!("Dropping transition to {next:?}: {err}");
+                }
+
+                let timer = Timer::from_seconds(effect.duration(), TimerMode::Once);
+                transition.phase = Some(Phase::In { effect, timer });
+            }
+            Some(Phase::In { .. }) => {
+                camera.set_mosaic(1);
+                for (entity, _) in &overlay {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+            None => unreachable!(),
+        }
+    }
+}
+
+impl<S: StateData> Transition<S> {
+    /// Begin a transition to the given state.
+    ///
+    /// Requests made while a transition is already running are ignored.
+    pub fn transition_to(&mut self, next: S, effect: Effect) {
+        if self.is_active() {
+            warn!("Ignoring transition to {next:?} while another transition is running");
+            return;
+        }
+
+        let timer = Timer::from_seconds(effect.duration(), TimerMode::Once);
+        self.phase = Some(Phase::Out {
+            next,
+            effect,
+            timer,
+        });
+    }
+
+    /// Returns `true` while a transition is running.
+    pub fn is_active(&self) -> bool {
+        self.phase.is_some()
+    }
+}
+
+impl Effect {
+    /// Get the duration of one half of the transition in seconds.
+    pub fn duration(&self) -> f32 {
+        match *self {
+            Self::Cut => 0.0,
+            Self::Fade(time)
+            | Self::Wipe(time)
+            | Self::Iris(time)
+            | Self::Dissolve(time)
+            | Self::Mosaic(time) => time,
+        }
+    }
+
+    fn mosaic_size(&self, coverage: f32) -> u32 {
+        match self {
+            Self::Mosaic(_) => 1 + (coverage * (MOSAIC_SIZE - 1.0)) as u32,
+            _ => 1,
+        }
+    }
+
+    /// Draw the effect into a full-screen bitmap.
+    fn render(&self, bitmap: &mut Bitmap, coverage: f32, incoming: bool) {
+        let black = Rgba8p::new(0.0, 0.0, 0.0, 1.0);
+        let clear = Rgba8p::new(0.0, 0.0, 0.0, 0.0);
+
+        match self {
+            Self::Cut => bitmap.clear(clear),
+            Self::Fade(_) | Self::Mosaic(_) => {
+                bitmap.clear(Rgba8p::new(0.0, 0.0, 0.0, coverage));
+            }
+            Self::Wipe(_) => {
+                let width = bitmap.width();
+                let edge = (coverage * width as f32) as u32;
+
+                // The curtain enters from the left and leaves to the right.
+                bitmap.fill_with(|x, _| {
+                    let covered = if incoming {
+                        x >= width - edge
+                    } else {
+                        x < edge
+                    };
+
+                    if covered {
+                        black
+                    } else {
+                        clear
+                    }
+                });
+            }
+            Self::Iris(_) => {
+                let center = Vec2::new(bitmap.width() as f32, bitmap.height() as f32) / 2.0;
+                let radius = center.length() * (1.0 - coverage);
+
+                bitmap.fill_with(|x, y| {
+                    let pos = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+
+                    if pos.distance(center) > radius {
+                        black
+                    } else {
+                        clear
+                    }
+                });
+            }
+            Self::Dissolve(_) => {
+                let width = bitmap.width() as u64;
+                let threshold = (coverage * u32::MAX as f32) as u32;
+
+                // Each pixel gets a fixed pseudorandom threshold, so the pattern is stable.
+                bitmap.fill_with(|x, y| {
+                    let mut rng = RngSource::new(y as u64 * width + x as u64);
+
+                    if coverage >= 1.0 || rng.u32() < threshold {
+                        black
+                    } else {
+                        clear
+                    }
+                });
+            }
+        }
+    }
+}
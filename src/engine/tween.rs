@@ -0,0 +1,305 @@
+use crate::engine::{Camera, Opacity, Tint};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::{collections::VecDeque, f32::consts::PI};
+
+#[derive(Debug)]
+pub(crate) struct TweenPlugin;
+
+/// Animates a property of the entity (or the [`Camera`]) over time.
+///
+/// Tweens are built from a sequence of steps which run one after the other. Each step animates
+/// from whatever the property's value is when the step begins, so steps can be chained freely:
+///
+/// ```ignore
+/// let tween = Tween::new(TweenTarget::Translation(start), 0.0, Easing::Linear)
+///     .then(TweenTarget::Translation(end), 1.0, Easing::BounceOut)
+///     .then(TweenTarget::Opacity(0.0), 0.5, Easing::QuadIn)
+///     .with_id(42);
+/// ```
+///
+/// The component removes itself when the last step finishes, and a [`TweenCompleted`] event is
+/// sent.
+#[derive(Component, Debug)]
+pub struct Tween {
+    steps: VecDeque<Step>,
+    elapsed: f32,
+    id: u64,
+}
+
+/// The property animated by one step of a [`Tween`], and its final value.
+#[derive(Copy, Clone, Debug)]
+pub enum TweenTarget {
+    /// The entity's [`Transform`] translation.
+    Translation(Vec3),
+    /// The entity's [`Tint`] color.
+    Tint(Vec4),
+    /// The entity's [`Opacity`].
+    Opacity(f32),
+    /// The [`Camera`] viewport translation.
+    CameraTranslation(Vec3),
+    /// Do nothing for the duration of the step.
+    Delay,
+}
+
+/// Easing curves for [`Tween`]s.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+/// Sent when a [`Tween`] has finished all of its steps.
+#[derive(Debug)]
+pub struct TweenCompleted {
+    pub entity: Entity,
+    pub id: u64,
+}
+
+#[derive(Debug)]
+struct Step {
+    target: TweenTarget,
+    duration: f32,
+    easing: Easing,
+    from: Option<Vec4>,
+}
+
+/// Tweens and the properties they can animate.
+type TweenQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Tween,
+        Option<&'static mut Transform>,
+        Option<&'static mut Tint>,
+        Option<&'static mut Opacity>,
+    ),
+>;
+
+impl Plugin for TweenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TweenCompleted>().add_system(Self::update);
+    }
+}
+
+impl TweenPlugin {
+    fn update(
+        mut commands: Commands,
+        mut query: TweenQuery,
+        mut camera: ResMut<Camera>,
+        mut events: EventWriter<TweenCompleted>,
+        time: Res<Time>,
+    ) {
+        for (entity, mut tween, mut transform, mut tint, mut opacity) in &mut query {
+            tween.elapsed += time.delta_seconds();
+
+            while let Some(step) = tween.steps.front() {
+                let duration = step.duration;
+                let t = if duration > 0.0 {
+                    (tween.elapsed / duration).min(1.0)
+                } else {
+                    1.0
+                };
+
+                let step = tween.steps.front_mut().unwrap();
+                let target = step.target;
+                let mut property = Property {
+                    transform: transform.as_deref_mut(),
+                    tint: tint.as_deref_mut(),
+                    opacity: opacity.as_deref_mut(),
+                    camera: &mut camera,
+                };
+                let from = *step.from.get_or_insert_with(|| property.get(target));
+                let value = from.lerp(target.to_vec4(), step.easing.apply(t));
+                property.set(target, value, &mut commands, entity);
+
+                if tween.elapsed < duration {
+                    break;
+                }
+
+                tween.elapsed -= duration;
+                tween.steps.pop_front();
+            }
+
+            if tween.steps.is_empty() {
+                commands.entity(entity).remove::<Tween>();
+                events.send(TweenCompleted {
+                    entity,
+                    id: tween.id,
+                });
+            }
+        }
+    }
+}
+
+/// Mutable access to every property a tween can animate.
+struct Property<'a> {
+    transform: Option<&'a mut Transform>,
+    tint: Option<&'a mut Tint>,
+    opacity: Option<&'a mut Opacity>,
+    camera: &'a mut Camera,
+}
+
+impl Property<'_> {
+    /// Read the current value of the property as a `Vec4`.
+    fn get(&self, target: TweenTarget) -> Vec4 {
+        match target {
+            TweenTarget::Translation(_) => self
+                .transform
+                .as_ref()
+                .map_or(Vec3::ZERO, |transform| transform.translation)
+                .extend(0.0),
+            TweenTarget::Tint(_) => self.tint.as_ref().map_or(Vec4::ONE, |tint| tint.0),
+            TweenTarget::Opacity(_) => {
+                Vec4::splat(self.opacity.as_ref().map_or(1.0, |opacity| opacity.0))
+            }
+            TweenTarget::CameraTranslation(_) => self.camera.transform().translation.extend(0.0),
+            TweenTarget::Delay => Vec4::ZERO,
+        }
+    }
+
+    /// Write a new value to the property. Missing `Tint` and `Opacity` components are inserted.
+    fn set(&mut self, target: TweenTarget, value: Vec4, commands: &mut Commands, entity: Entity) {
+        match target {
+            TweenTarget::Translation(_) => {
+                if let Some(transform) = self.transform.as_mut() {
+                    transform.translation = value.truncate();
+                }
+            }
+            TweenTarget::Tint(_) => match self.tint.as_mut() {
+                Some(tint) => tint.0 = value,
+                None => {
+                    commands.entity(entity).insert(Tint(value));
+                }
+            },
+            TweenTarget::Opacity(_) => match self.opacity.as_mut() {
+                Some(opacity) => opacity.0 = value.x,
+                None => {
+                    commands.entity(entity).insert(Opacity(value.x));
+                }
+            },
+            TweenTarget::CameraTranslation(_) => {
+                self.camera.transform_mut().translation = value.truncate();
+            }
+            TweenTarget::Delay => (),
+        }
+    }
+}
+
+impl Tween {
+    /// Create a tween with a single step.
+    pub fn new(target: TweenTarget, duration: f32, easing: Easing) -> Self {
+        Self {
+            steps: VecDeque::new(),
+            elapsed: 0.0,
+            id: 0,
+        }
+        .then(target, duration, easing)
+    }
+
+    /// Append a step to the sequence.
+    pub fn then(mut self, target: TweenTarget, duration: f32, easing: Easing) -> Self {
+        self.steps.push_back(Step {
+            target,
+            duration,
+            easing,
+            from: None,
+        });
+
+        self
+    }
+
+    /// Append a pause to the sequence.
+    pub fn delay(self, duration: f32) -> Self {
+        self.then(TweenTarget::Delay, duration, Easing::Linear)
+    }
+
+    /// Set the ID that is reported by [`TweenCompleted`].
+    pub fn with_id(mut self, id: u64) -> Self {
+        self.id = id;
+
+        self
+    }
+}
+
+impl TweenTarget {
+    fn to_vec4(self) -> Vec4 {
+        match self {
+            Self::Translation(translation) | Self::CameraTranslation(translation) => {
+                translation.extend(0.0)
+            }
+            Self::Tint(color) => color,
+            Self::Opacity(opacity) => Vec4::splat(opacity),
+            Self::Delay => Vec4::ZERO,
+        }
+    }
+}
+
+impl Easing {
+    /// Map linear progress `t` in `[0, 1]` onto the easing curve.
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Self::Linear => t,
+            Self::QuadIn => t * t,
+            Self::QuadOut => 1.0 - (1.0 - t).powi(2),
+            Self::QuadInOut => in_out(t, |t| t * t),
+            Self::CubicIn => t.powi(3),
+            Self::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Self::CubicInOut => in_out(t, |t| t.powi(3)),
+            Self::ElasticIn => elastic_in(t),
+            Self::ElasticOut => 1.0 - elastic_in(1.0 - t),
+            Self::ElasticInOut => in_out(t, elastic_in),
+            Self::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Self::BounceOut => bounce_out(t),
+            Self::BounceInOut => in_out(t, |t| 1.0 - bounce_out(1.0 - t)),
+        }
+    }
+}
+
+/// Build a symmetric in-out curve from an "in" curve.
+fn in_out(t: f32, ease_in: impl Fn(f32) -> f32) -> f32 {
+    if t < 0.5 {
+        ease_in(t * 2.0) / 2.0
+    } else {
+        1.0 - ease_in((1.0 - t) * 2.0) / 2.0
+    }
+}
+
+fn elastic_in(t: f32) -> f32 {
+    if t <= 0.0 || t >= 1.0 {
+        t.clamp(0.0, 1.0)
+    } else {
+        -(2.0_f32.powf(10.0 * t - 10.0)) * ((t * 10.0 - 10.75) * (2.0 * PI / 3.0)).sin()
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
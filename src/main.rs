@@ -1,5 +1,4 @@
 use bevy::{prelude::*, window::WindowResizeConstraints};
-use bevy_embedded_assets::EmbeddedAssetPlugin;
 use bevy_kira_audio::prelude::*;
 use odonata::{
     consts::APP_NAME,
@@ -9,10 +8,14 @@ use odonata::{
 
 fn main() {
     let config = ConfigState::default();
-    let (width, height) = config.screen_resolution();
+    let (window_width, window_height) = config.window_size();
+    let mode = config.display_mode().window_mode();
 
-    let window_width = width as f32 * 2.0;
-    let window_height = height as f32 * 2.0;
+    let initial_state = if config.skip_intro() {
+        GameState::Title
+    } else {
+        GameState::Intro
+    };
 
     App::new()
         .add_plugins(
@@ -28,18 +31,18 @@ fn main() {
                             min_height: window_height,
                             ..default()
                         },
-                        // mode: bevy::window::WindowMode::BorderlessFullscreen,
+                        mode,
                         fit_canvas_to_parent: true,
                         ..default()
                     },
                     ..default()
                 })
-                .add_before::<bevy::asset::AssetPlugin, _>(EmbeddedAssetPlugin),
+                .add_before::<bevy::asset::AssetPlugin, _>(config.asset_plugin()),
         )
-        .add_plugin(EnginePlugin)
         .add_plugin(AudioPlugin)
+        .add_plugin(EnginePlugin)
         .add_plugin(ScenePlugin)
-        .add_state(GameState::Intro)
+        .add_state(initial_state)
         .add_system(bevy::window::close_on_esc)
         .run();
 }
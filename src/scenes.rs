@@ -1,21 +1,44 @@
+use crate::engine::{CutscenePlugin, Music, MusicPlugin, Track, TransitionPlugin};
 use bevy::prelude::*;
+use serde::Deserialize;
 
 mod intro;
+mod options;
+mod pages;
 mod title;
 
 #[derive(Debug)]
 pub struct ScenePlugin;
 
-#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize)]
 pub enum GameState {
     Intro,
     Title,
+    Options,
+    HighScores,
+    Credits,
     Game,
 }
 
 impl Plugin for ScenePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(intro::IntroPlugin)
+        app.add_plugin(TransitionPlugin::<GameState>::default())
+            .add_plugin(CutscenePlugin::<GameState>::default())
+            .add_plugin(MusicPlugin::<GameState>::default())
+            .add_plugin(intro::IntroPlugin)
+            .add_plugin(options::OptionsPlugin)
+            .add_plugin(pages::PagesPlugin)
             .add_plugin(title::TitlePlugin);
+
+        // The title music keeps playing in the submenus.
+        let mut music = app.world.resource_mut::<Music<GameState>>();
+        for state in [
+            GameState::Title,
+            GameState::Options,
+            GameState::HighScores,
+            GameState::Credits,
+        ] {
+            music.set_track(state, Track::new("music/getting-started.xm"));
+        }
     }
 }
@@ -0,0 +1,377 @@
+use super::GameState;
+use crate::engine::{
+    text_size, Action, ActionState, AspectRatio, Bitmap, BitmapCache, ConfigState, DisplayMode,
+    Effect, Filter, KeyBindings, SaveEvent, Scaling, ScreenSpace, Sfxr, Tiled, Tint, Transition,
+    UiChannel, MAX_WINDOW_SCALE,
+};
+use bevy::prelude::*;
+use bevy_kira_audio::prelude::*;
+use pix::rgb::Rgba8p;
+
+/// The options menu edits the [`ConfigState`] live. Every change is applied and saved immediately.
+#[derive(Debug)]
+pub(crate) struct OptionsPlugin;
+
+#[derive(Component, Debug)]
+struct OptionsScreen;
+
+/// The options menu state.
+#[derive(Debug, Resource)]
+struct Menu {
+    selected: usize,
+    rows: Vec<Row>,
+    heading: Entity,
+    lines: Vec<Entity>,
+    capturing: bool,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Row {
+    AspectRatio,
+    WindowScale,
+    DisplayMode,
+    Scaling,
+    Filter,
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    UiVolume,
+    ShowFps,
+    SkipIntro,
+    Key(Action),
+    Back,
+}
+
+/// Vertical position of the heading.
+const HEADING_TOP: f32 = 14.0;
+
+/// Vertical position of the first row.
+const MENU_TOP: f32 = 34.0;
+
+/// Vertical distance between rows.
+const MENU_SPACING: f32 = 11.0;
+
+/// Labels are padded to this many characters so that the values line up.
+const LABEL_WIDTH: usize = 14;
+
+/// How much the volume changes with each press.
+const VOLUME_STEP: f32 = 0.1;
+
+impl Plugin for OptionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::Options).with_system(Self::enter))
+            .add_system_set(
+                SystemSet::on_update(GameState::Options)
+                    .with_system(Self::update.before(bevy::window::close_on_esc))
+                    .with_system(Self::draw.after(Self::update)),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Options).with_system(Self::exit));
+    }
+}
+
+impl OptionsPlugin {
+    fn enter(
+        mut commands: Commands,
+        mut cache: ResMut<BitmapCache>,
+        asset_server: Res<AssetServer>,
+    ) {
+        // Spawn the background
+        let transform = Transform::from_xyz(0.0, 0.0, 1.0);
+        let bitmap = cache.get_or_create("images/bg2.png", &asset_server);
+        commands.spawn((bitmap, transform, Tiled, OptionsScreen));
+
+        // Spawn the heading and one line of text per row. The text is drawn by `draw`.
+        let heading = commands
+            .spawn((
+                Bitmap::from_text("OPTIONS", Rgba8p::new(1.0, 1.0, 1.0, 1.0)),
+                Transform::IDENTITY,
+                Tint::YELLOW,
+                ScreenSpace,
+                OptionsScreen,
+            ))
+            .id();
+        let rows = Row::all();
+        let lines = rows
+            .iter()
+            .map(|_| {
+                let bitmap = Bitmap::with_clear(1, 1);
+                commands
+                    .spawn((bitmap, Transform::IDENTITY, ScreenSpace, OptionsScreen))
+                    .id()
+            })
+            .collect();
+
+        commands.insert_resource(Menu {
+            selected: 0,
+            rows,
+            heading,
+            lines,
+            capturing: false,
+        });
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn update(
+        mut menu: ResMut<Menu>,
+        mut transition: ResMut<Transition<GameState>>,
+        mut save: EventWriter<SaveEvent>,
+        actions: Res<ActionState>,
+        mut keys: ResMut<Input<KeyCode>>,
+        asset_server: Res<AssetServer>,
+        config: Res<ConfigState>,
+        ui: Res<AudioChannel<UiChannel>>,
+        mut sfxr: Sfxr,
+    ) {
+        if transition.is_active() {
+            return;
+        }
+
+        // Waiting for a new key binding
+        if menu.capturing {
+            if keys.clear_just_pressed(KeyCode::Escape) {
+                // Cancel without letting Escape quit the game.
+                menu.capturing = false;
+                ui.play(asset_server.load("sfx/blips.sfxr.json#blip7"));
+            } else if let Some(&key) = keys.get_just_pressed().next() {
+                // Reserved keys are rejected and capture continues.
+                if KeyBindings::RESERVED.contains(&key) {
+                    return;
+                }
+                if let Row::Key(action) = menu.rows[menu.selected] {
+                    save.send(SaveEvent::KeyBinding(action, key));
+                }
+                menu.capturing = false;
+                ui.play(asset_server.load("sfx/blips.sfxr.json#blip7"));
+            }
+            return;
+        }
+
+        if actions.just_pressed(Action::Cancel) {
+            transition.transition_to(GameState::Title, Effect::Wipe(0.3));
+            return;
+        }
+
+        let count = menu.rows.len();
+        if actions.just_pressed(Action::Up) {
+            menu.selected = (menu.selected + count - 1) % count;
+            ui.play(sfxr.mutated("sfx/blips.sfxr.json#blip1", 0.02));
+        } else if actions.just_pressed(Action::Down) {
+            menu.selected = (menu.selected + 1) % count;
+            ui.play(sfxr.mutated("sfx/blips.sfxr.json#blip1", 0.02));
+        }
+
+        let step = if actions.just_pressed(Action::Left) {
+            -1
+        } else if actions.just_pressed(Action::Right) {
+            1
+        } else {
+            0
+        };
+        let confirm = actions.just_pressed(Action::Confirm);
+
+        let row = menu.rows[menu.selected];
+        let event = match row {
+            Row::AspectRatio if step != 0 => Some(SaveEvent::AspectRatio(cycle(
+                &AspectRatio::ALL,
+                config.aspect_ratio(),
+                step,
+            ))),
+            Row::WindowScale if step != 0 => {
+                let scale = config.window_scale() as i32 + step;
+                let scale = scale.clamp(1, MAX_WINDOW_SCALE as i32) as u32;
+
+                Some(SaveEvent::WindowScale(scale))
+            }
+            Row::DisplayMode if step != 0 => Some(SaveEvent::DisplayMode(cycle(
+                &DisplayMode::ALL,
+                config.display_mode(),
+                step,
+            ))),
+            Row::Scaling if step != 0 => Some(SaveEvent::Scaling(cycle(
+                &Scaling::ALL,
+                config.scaling(),
+                step,
+            ))),
+            Row::Filter if step != 0 => Some(SaveEvent::Filter(cycle(
+                &Filter::ALL,
+                config.filter(),
+                step,
+            ))),
+            Row::MasterVolume if step != 0 => Some(SaveEvent::MasterVolume(
+                config.master_volume() + VOLUME_STEP * step as f32,
+            )),
+            Row::MusicVolume if step != 0 => Some(SaveEvent::MusicVolume(
+                config.music_volume() + VOLUME_STEP * step as f32,
+            )),
+            Row::SfxVolume if step != 0 => Some(SaveEvent::SfxVolume(
+                config.sfx_volume() + VOLUME_STEP * step as f32,
+            )),
+            Row::UiVolume if step != 0 => Some(SaveEvent::UiVolume(
+                config.ui_volume() + VOLUME_STEP * step as f32,
+            )),
+            Row::ShowFps if step != 0 || confirm => Some(SaveEvent::ShowFps(!config.show_fps())),
+            Row::SkipIntro if step != 0 || confirm => {
+                Some(SaveEvent::SkipIntro(!config.skip_intro_enabled()))
+            }
+            Row::Key(_) if confirm => {
+                menu.capturing = true;
+                None
+            }
+            Row::Back if confirm => {
+                transition.transition_to(GameState::Title, Effect::Wipe(0.3));
+                None
+            }
+            _ => None,
+        };
+
+        if let Some(event) = event {
+            save.send(event);
+            ui.play(sfxr.mutated("sfx/blips.sfxr.json#blip1", 0.02));
+        } else if confirm {
+            ui.play(asset_server.load("sfx/blips.sfxr.json#blip7"));
+        }
+    }
+
+    /// Redraw every row when the selection or the config changes.
+    fn draw(
+        mut commands: Commands,
+        menu: Res<Menu>,
+        config: Res<ConfigState>,
+        mut query: Query<(&mut Bitmap, &mut Transform)>,
+    ) {
+        if !menu.is_changed() && !config.is_changed() {
+            return;
+        }
+
+        let (width, _) = config.screen_resolution();
+        let white = Rgba8p::new(1.0, 1.0, 1.0, 1.0);
+        let lines: Vec<_> = menu
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                if menu.capturing && i == menu.selected {
+                    format!("{:<LABEL_WIDTH$}PRESS A KEY", row.label())
+                } else {
+                    format!("{:<LABEL_WIDTH$}{}", row.label(), row.value(&config))
+                }
+            })
+            .collect();
+
+        // All rows are left-aligned with the widest row centered on the screen.
+        let widest = lines
+            .iter()
+            .map(|line| text_size(line).0)
+            .max()
+            .unwrap_or_default();
+        let x = (width.saturating_sub(widest) / 2) as f32;
+
+        for (i, (line, &entity)) in lines.iter().zip(&menu.lines).enumerate() {
+            if let Ok((mut bitmap, mut transform)) = query.get_mut(entity) {
+                *bitmap = Bitmap::from_text(line, white);
+                *transform = Transform::from_xyz(x, MENU_TOP + MENU_SPACING * i as f32, 2.0);
+            }
+
+            let tint = if i == menu.selected {
+                Tint::YELLOW
+            } else {
+                Tint::WHITE
+            };
+            commands.entity(entity).insert(tint);
+        }
+
+        // The heading is centered, so it moves when the aspect ratio changes.
+        if let Ok((bitmap, mut transform)) = query.get_mut(menu.heading) {
+            let x = ((width - bitmap.width()) / 2) as f32;
+            *transform = Transform::from_xyz(x, HEADING_TOP, 2.0);
+        }
+    }
+
+    fn exit(mut commands: Commands, entities: Query<Entity, With<OptionsScreen>>) {
+        commands.remove_resource::<Menu>();
+        for entity in &entities {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+impl Row {
+    fn all() -> Vec<Self> {
+        let settings = [
+            Self::AspectRatio,
+            Self::WindowScale,
+            Self::DisplayMode,
+            Self::Scaling,
+            Self::Filter,
+            Self::MasterVolume,
+            Self::MusicVolume,
+            Self::SfxVolume,
+            Self::UiVolume,
+            Self::ShowFps,
+            Self::SkipIntro,
+        ];
+        let keys = Action::ALL.into_iter().map(Self::Key);
+
+        settings
+            .into_iter()
+            .chain(keys)
+            .chain([Self::Back])
+            .collect()
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Self::AspectRatio => "ASPECT RATIO".to_string(),
+            Self::WindowScale => "WINDOW SCALE".to_string(),
+            Self::DisplayMode => "DISPLAY".to_string(),
+            Self::Scaling => "SCALING".to_string(),
+            Self::Filter => "FILTER".to_string(),
+            Self::MasterVolume => "MASTER VOLUME".to_string(),
+            Self::MusicVolume => "MUSIC VOLUME".to_string(),
+            Self::SfxVolume => "SFX VOLUME".to_string(),
+            Self::UiVolume => "UI VOLUME".to_string(),
+            Self::ShowFps => "SHOW FPS".to_string(),
+            Self::SkipIntro => "SKIP INTRO".to_string(),
+            Self::Key(action) => format!("KEY {action:?}"),
+            Self::Back => "BACK".to_string(),
+        }
+    }
+
+    fn value(&self, config: &ConfigState) -> String {
+        match self {
+            Self::AspectRatio => config.aspect_ratio().label().to_string(),
+            Self::WindowScale => format!("{}X", config.window_scale()),
+            Self::DisplayMode => config.display_mode().label().to_string(),
+            Self::Scaling => config.scaling().label().to_string(),
+            Self::Filter => config.filter().label().to_string(),
+            Self::MasterVolume => volume(config.master_volume()),
+            Self::MusicVolume => volume(config.music_volume()),
+            Self::SfxVolume => volume(config.sfx_volume()),
+            Self::UiVolume => volume(config.ui_volume()),
+            Self::ShowFps => on_off(config.show_fps()),
+            Self::SkipIntro => on_off(config.skip_intro_enabled()),
+            Self::Key(action) => config
+                .key_bindings()
+                .keys(*action)
+                .first()
+                .map_or_else(|| "NONE".to_string(), |key| format!("{key:?}")),
+            Self::Back => String::new(),
+        }
+    }
+}
+
+/// Step forwards or backwards through a list of choices, wrapping around at either end.
+fn cycle<T: Copy + PartialEq>(all: &[T], current: T, step: i32) -> T {
+    let index = all.iter().position(|&value| value == current);
+    let index = index.unwrap_or_default() as i32 + step;
+
+    all[index.rem_euclid(all.len() as i32) as usize]
+}
+
+fn on_off(value: bool) -> String {
+    if value { "ON" } else { "OFF" }.to_string()
+}
+
+fn volume(value: f32) -> String {
+    format!("{:.0}%", value * 100.0)
+}
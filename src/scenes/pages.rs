@@ -0,0 +1,93 @@
+use super::GameState;
+use crate::engine::{
+    Action, ActionState, Bitmap, BitmapCache, ConfigState, Effect, ScreenSpace, Tiled, Tint,
+    Transition,
+};
+use bevy::prelude::*;
+use pix::rgb::Rgba8p;
+
+/// Simple text pages that are reached from the title menu.
+#[derive(Debug)]
+pub(crate) struct PagesPlugin;
+
+#[derive(Component, Debug)]
+struct PageScreen;
+
+struct Page {
+    state: GameState,
+    heading: &'static str,
+    body: &'static str,
+}
+
+static PAGES: [Page; 2] = [
+    Page {
+        state: GameState::HighScores,
+        heading: "HIGH SCORES",
+        body: "NO HIGH SCORES YET",
+    },
+    Page {
+        state: GameState::Credits,
+        heading: "CREDITS",
+        body: "A GAME BY BLIPJOY\n\n\nMADE WITH BEVY",
+    },
+];
+
+impl Plugin for PagesPlugin {
+    fn build(&self, app: &mut App) {
+        for page in &PAGES {
+            let enter = move |commands: Commands,
+                              cache: ResMut<BitmapCache>,
+                              asset_server: Res<AssetServer>,
+                              config: Res<ConfigState>| {
+                Self::enter(commands, cache, asset_server, config, page);
+            };
+
+            app.add_system_set(SystemSet::on_enter(page.state.clone()).with_system(enter))
+                .add_system_set(SystemSet::on_update(page.state.clone()).with_system(Self::update))
+                .add_system_set(SystemSet::on_exit(page.state.clone()).with_system(Self::exit));
+        }
+    }
+}
+
+impl PagesPlugin {
+    fn enter(
+        mut commands: Commands,
+        mut cache: ResMut<BitmapCache>,
+        asset_server: Res<AssetServer>,
+        config: Res<ConfigState>,
+        page: &Page,
+    ) {
+        let (width, _) = config.screen_resolution();
+
+        // Spawn the background
+        let transform = Transform::from_xyz(0.0, 0.0, 1.0);
+        let bitmap = cache.get_or_create("images/bg2.png", &asset_server);
+        commands.spawn((bitmap, transform, Tiled, PageScreen));
+
+        // Spawn the heading and body text, centered horizontally
+        let white = Rgba8p::new(1.0, 1.0, 1.0, 1.0);
+        for (text, y, tint) in [
+            (page.heading, 40.0, Tint::YELLOW),
+            (page.body, 80.0, Tint::WHITE),
+        ] {
+            let bitmap = Bitmap::from_text(text, white);
+            let x = ((width - bitmap.width()) / 2) as f32;
+            let transform = Transform::from_xyz(x, y, 2.0);
+
+            commands.spawn((bitmap, transform, tint, ScreenSpace, PageScreen));
+        }
+    }
+
+    fn update(mut transition: ResMut<Transition<GameState>>, actions: Res<ActionState>) {
+        let back = actions.just_pressed(Action::Confirm) || actions.just_pressed(Action::Cancel);
+        if back && !transition.is_active() {
+            transition.transition_to(GameState::Title, Effect::Wipe(0.3));
+        }
+    }
+
+    fn exit(mut commands: Commands, entities: Query<Entity, With<PageScreen>>) {
+        for entity in &entities {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use super::GameState;
-use crate::engine::{BitmapCache, Camera, ConfigState, ScreenSpace, Tiled};
-use bevy::prelude::*;
+use crate::engine::{
+    text_size, Action, ActionState, Bitmap, BitmapCache, CameraController, CameraMode, ConfigState,
+    Easing, Effect, ScreenSpace, Sfxr, Tiled, Tint, Transition, Tween, TweenTarget, UiChannel,
+};
+use bevy::{app::AppExit, prelude::*};
 use bevy_kira_audio::prelude::*;
 use pix::rgb::Rgba8p;
 
@@ -16,6 +19,35 @@ struct Motion {
     magnitude: f32,
 }
 
+/// The title menu state.
+#[derive(Debug, Resource)]
+struct Menu {
+    selected: usize,
+    items: Vec<Entity>,
+    cursor: Entity,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum MenuItem {
+    Start,
+    Options,
+    HighScores,
+    Credits,
+    Quit,
+}
+
+/// Vertical position of the first menu item.
+const MENU_TOP: f32 = 155.0;
+
+/// Vertical distance between menu items.
+const MENU_SPACING: f32 = 14.0;
+
+/// How far the selected menu item is nudged to the right.
+const MENU_NUDGE: f32 = 6.0;
+
+/// Horizontal position of the cursor, relative to the menu items.
+const CURSOR_OFFSET: f32 = -4.0;
+
 impl Default for Motion {
     fn default() -> Self {
         Self {
@@ -29,7 +61,11 @@ impl Plugin for TitlePlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Motion>()
             .add_system_set(SystemSet::on_enter(GameState::Title).with_system(Self::enter))
-            .add_system_set(SystemSet::on_update(GameState::Title).with_system(Self::update))
+            .add_system_set(
+                SystemSet::on_update(GameState::Title)
+                    .with_system(Self::update)
+                    .with_system(Self::menu),
+            )
             .add_system_set(SystemSet::on_exit(GameState::Title).with_system(Self::exit));
     }
 }
@@ -40,42 +76,175 @@ impl TitlePlugin {
         mut cache: ResMut<BitmapCache>,
         asset_server: Res<AssetServer>,
         config: Res<ConfigState>,
-        audio: Res<Audio>,
     ) {
-        audio
-            .play(asset_server.load("music/getting-started.ogg"))
-            .looped();
-
         // Spawn the background
         let transform = Transform::from_xyz(0.0, 0.0, 1.0);
         let bitmap = cache.get_or_create("images/bg1.png", &asset_server);
         commands.spawn((bitmap, transform, Tiled, TitleScreen));
 
-        // Spawn the title logo
-        let (width, height) = config.screen_resolution();
-        let x = (width / 2) as f32;
-        let transform = Transform::from_xyz(x - 120.0, 65.0, 2.0);
+        // Spawn the title logo, sliding in from above the screen
+        let (width, _) = config.screen_resolution();
+        let x = (width / 2) as f32 - 120.0;
+        let transform = Transform::from_xyz(x, -100.0, 2.0);
         let bitmap = cache.get_or_create("images/odonata.png", &asset_server);
-        commands.spawn((bitmap, transform, ScreenSpace, TitleScreen));
+        let target = TweenTarget::Translation(Vec3::new(x, 65.0, 2.0));
+        let tween = Tween::new(target, 1.5, Easing::BounceOut);
+        commands.spawn((bitmap, transform, tween, ScreenSpace, TitleScreen));
+
+        // Spawn the menu
+        let white = Rgba8p::new(1.0, 1.0, 1.0, 1.0);
+        let items = MenuItem::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let transform = Transform::from_xyz(MenuItem::left(width), MenuItem::top(i), 3.0);
+                let bitmap = Bitmap::from_text(item.label(), white);
+
+                commands
+                    .spawn((bitmap, transform, ScreenSpace, TitleScreen))
+                    .id()
+            })
+            .collect();
+
+        let cursor = Bitmap::from_text(">", white);
+        let x = MenuItem::left(width) + CURSOR_OFFSET;
+        let transform = Transform::from_xyz(x, MenuItem::top(0), 3.0);
+        let tint = Tint::YELLOW;
+        let cursor = commands
+            .spawn((cursor, transform, tint, ScreenSpace, TitleScreen))
+            .id();
 
-        // Spawn the fade layer
-        let color = Rgba8p::new(0.0, 0.0, 0.0, 1.0);
-        let fade_bundle = Camera::fade_in(1.0, width, height, color);
-        commands.spawn(fade_bundle).insert(TitleScreen);
+        let menu = Menu {
+            selected: 0,
+            items,
+            cursor,
+        };
+        menu.highlight(&mut commands, width, None);
+        commands.insert_resource(menu);
     }
 
-    fn update(time: Res<Time>, mut camera: ResMut<Camera>, mut motion: ResMut<Motion>) {
-        let delta = time.delta().as_secs_f32();
-        let velocity = Quat::from_rotation_z(motion.angle) * Vec3::X * motion.magnitude;
+    fn update(mut controller: ResMut<CameraController>, mut motion: ResMut<Motion>) {
+        let velocity = Vec2::new(motion.angle.cos(), motion.angle.sin()) * motion.magnitude;
 
-        camera.transform_mut().translation += velocity * delta;
+        controller.mode = CameraMode::AutoScroll(velocity);
 
         motion.angle += 0.000033;
     }
 
-    fn exit(mut commands: Commands, entities: Query<Entity, With<TitleScreen>>) {
+    #[allow(clippy::too_many_arguments)]
+    fn menu(
+        mut commands: Commands,
+        mut menu: ResMut<Menu>,
+        mut transition: ResMut<Transition<GameState>>,
+        mut app_exit: EventWriter<AppExit>,
+        actions: Res<ActionState>,
+        asset_server: Res<AssetServer>,
+        config: Res<ConfigState>,
+        ui: Res<AudioChannel<UiChannel>>,
+        mut sfxr: Sfxr,
+    ) {
+        if transition.is_active() {
+            return;
+        }
+
+        let count = MenuItem::ALL.len();
+        let previous = menu.selected;
+        if actions.just_pressed(Action::Up) {
+            menu.selected = (menu.selected + count - 1) % count;
+        } else if actions.just_pressed(Action::Down) {
+            menu.selected = (menu.selected + 1) % count;
+        }
+
+        if menu.selected != previous {
+            ui.play(sfxr.mutated("sfx/blips.sfxr.json#blip1", 0.02));
+
+            let (width, _) = config.screen_resolution();
+            menu.highlight(&mut commands, width, Some(previous));
+        }
+
+        if !actions.just_pressed(Action::Confirm) {
+            return;
+        }
+
+        ui.play(asset_server.load("sfx/blips.sfxr.json#blip7"));
+
+        match MenuItem::ALL[menu.selected] {
+            MenuItem::Start => transition.transition_to(GameState::Game, Effect::Fade(1.0)),
+            MenuItem::Options => transition.transition_to(GameState::Options, Effect::Wipe(0.3)),
+            MenuItem::HighScores => {
+                transition.transition_to(GameState::HighScores, Effect::Wipe(0.3));
+            }
+            MenuItem::Credits => transition.transition_to(GameState::Credits, Effect::Wipe(0.3)),
+            MenuItem::Quit => app_exit.send(AppExit),
+        }
+    }
+
+    fn exit(
+        mut commands: Commands,
+        mut controller: ResMut<CameraController>,
+        entities: Query<Entity, With<TitleScreen>>,
+    ) {
+        controller.mode = CameraMode::Manual;
+        commands.remove_resource::<Menu>();
         for entity in &entities {
             commands.entity(entity).despawn_recursive();
         }
     }
 }
+
+impl Menu {
+    /// Animate the cursor and selected item into place, and return the previous item to normal.
+    fn highlight(&self, commands: &mut Commands, width: u32, previous: Option<usize>) {
+        let x = MenuItem::left(width);
+
+        if let Some(previous) = previous {
+            let target = TweenTarget::Translation(Vec3::new(x, MenuItem::top(previous), 3.0));
+
+            commands
+                .entity(self.items[previous])
+                .insert((Tween::new(target, 0.15, Easing::QuadOut), Tint::WHITE));
+        }
+
+        let y = MenuItem::top(self.selected);
+        let target = TweenTarget::Translation(Vec3::new(x + MENU_NUDGE, y, 3.0));
+        commands
+            .entity(self.items[self.selected])
+            .insert((Tween::new(target, 0.15, Easing::QuadOut), Tint::YELLOW));
+
+        let target = TweenTarget::Translation(Vec3::new(x + CURSOR_OFFSET, y, 3.0));
+        commands
+            .entity(self.cursor)
+            .insert(Tween::new(target, 0.15, Easing::QuadOut));
+    }
+}
+
+impl MenuItem {
+    const ALL: [Self; 5] = [
+        Self::Start,
+        Self::Options,
+        Self::HighScores,
+        Self::Credits,
+        Self::Quit,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Start => "START",
+            Self::Options => "OPTIONS",
+            Self::HighScores => "HIGH SCORES",
+            Self::Credits => "CREDITS",
+            Self::Quit => "QUIT",
+        }
+    }
+
+    /// All items are left-aligned with the widest label centered on the screen.
+    fn left(width: u32) -> f32 {
+        let (widest, _) = text_size(Self::HighScores.label());
+
+        ((width - widest) / 2) as f32
+    }
+
+    fn top(index: usize) -> f32 {
+        MENU_TOP + MENU_SPACING * index as f32
+    }
+}